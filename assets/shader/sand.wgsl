@@ -4,11 +4,13 @@
 @group(0) @binding(1) var uSampler: sampler;
 
 
+// 字段顺序与 pipeline_sand.rs 中的 SandUniform 一致
 struct SandUniform{
-    isSnapshot: u32,
     t: f32,
     dpi: f32,
     resolution: vec2<f32>,
+    isSnapshot: u32,
+    customColor: u32,
 }
 
 
@@ -40,6 +42,27 @@ fn hsv2rgb(hsv: vec3<f32>) -> vec3<f32> {
     }
 }
 
+fn rgb2hsv(c: vec3<f32>) -> vec3<f32> {
+    let max_c = max(c.r, max(c.g, c.b));
+    let min_c = min(c.r, min(c.g, c.b));
+    let delta = max_c - min_c;
+    var h: f32 = 0.0;
+    if (delta > 0.0) {
+        if (max_c == c.r) {
+            h = mod1((c.g - c.b) / delta, 6.0);
+        } else if (max_c == c.g) {
+            h = (c.b - c.r) / delta + 2.0;
+        } else {
+            h = (c.r - c.g) / delta + 4.0;
+        }
+    }
+    var s: f32 = 0.0;
+    if (max_c > 0.0) {
+        s = delta / max_c;
+    }
+    return vec3<f32>(h / 6.0, s, max_c);
+}
+
 fn snoise3(p: vec3<f32>) -> f32 {
     let perm = vec3<f32>(151.0, 160.0, 170.0);
     let  p_ = fract(p);
@@ -163,6 +186,42 @@ fn main(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
         hue = fract(fract(data.b * 2.0) * 0.5) - 0.3;
         saturation = 0.7 * (data.g + 0.4) + data.b * 0.2;
         lightness = 0.9 * (data.g + 0.9);
+    } else if (type_val == 20) { // snow
+        hue = 0.6;
+        saturation = 0.12;
+        lightness = 0.95 + data.g * 0.1;
+    } else if (type_val == 21) { // 自定义材质 X，颜色来自配置文件
+        let hsv = rgb2hsv(unpack4x8unorm(value.customColor).rgb);
+        hue = hsv.x;
+        saturation = hsv.y;
+        lightness = hsv.z * (0.85 + data.g * 0.3);
+    } else if (type_val == 22) { // lightning
+        hue = 0.15;
+        saturation = 0.3;
+        lightness = 1.2;
+        // 轨迹（b > 0）随剩余时间变暗
+        if (data.b > 0.0) {
+            saturation = 0.5;
+            lightness = 0.8 + data.b * 15.0;
+        }
+    } else if (type_val == 23) { // steam
+        hue = 0.6;
+        saturation = 0.05;
+        // 剩余热量（b）越少越接近水的颜色
+        lightness = 0.8 + data.b * 0.4 + noise * 0.05;
+    } else if (type_val == 24) { // gunpowder
+        hue = 0.0;
+        saturation = 0.05;
+        lightness = 0.3 + data.g * 0.2;
+    } else if (type_val == 25) { // smoke
+        hue = 0.0;
+        saturation = 0.0;
+        // 剩余寿命（b）越少越淡
+        lightness = 0.9 - data.b * 1.5 + noise * 0.05;
+    } else if (type_val == 26) { // ash
+        hue = 0.08;
+        saturation = 0.05;
+        lightness = 0.55 + data.g * 0.2;
     }
 
 
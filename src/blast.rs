@@ -0,0 +1,114 @@
+use rand::Rng;
+use crate::boundary::Resolved;
+use crate::species::Species;
+use crate::universe::{Cell, Universe, EMPTY_CELL};
+
+// 爆炸：以 (x, y) 为圆心、radius 为半径，强度从圆心的 255 线性衰减到边缘的 0。
+// 半径可以超过 SandApi 的 ±2，所以物种规则只能通过 SandApi::explode 请求爆炸，
+// 请求排在 Universe.blasts 里，本 tick 所有细胞更新完之后由 apply_blasts 按请求顺序处理
+// （分块 tick 时按写回顺序合并，结果与线程数无关）。
+//
+// 圆内的每个格子：
+//   1. 强度超过注册表里的 toughness：被炸毁，变成空气
+//   2. 强度超过 toughness 的一半：沿半径方向被推开，落点是空气时才移动；从外圈往里处理，先炸空、推开的给后面腾出位置
+//   3. 温度升高到强度的两倍
+// 所有格子处理完之后，圆内仍然是空气的格子（原来的空气和被炸毁的）一半概率留下碎屑：内圈是火，外圈是烟。
+// 碎屑放在推开之后，否则空气先被碎屑填满，东西就推不动了。
+// 被炸到的火药会在同一 tick 里接着爆炸（半径相同），所以一堆火药会一次炸完。
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Blast {
+    pub x: i32,
+    pub y: i32,
+    pub radius: i32,
+}
+
+impl Universe {
+    pub(crate) fn apply_blasts(&mut self) {
+        let mut i = 0;
+        while i < self.blasts.len() {
+            let blast = self.blasts[i];
+            self.blast(blast);
+            i += 1;
+        }
+        self.blasts.clear();
+    }
+
+    fn blast(&mut self, blast: Blast) {
+        let radius = blast.radius.max(1);
+        let mut offsets = Vec::new();
+        for ox in -radius..=radius {
+            for oy in -radius..=radius {
+                let d2 = ox * ox + oy * oy;
+                if d2 <= radius * radius {
+                    offsets.push((d2, ox, oy));
+                }
+            }
+        }
+        offsets.sort_by(|a, b| b.0.cmp(&a.0));
+
+        // 炸空的格子和它是否在内圈，推开之后再放碎屑
+        let mut cleared = Vec::new();
+        for (d2, ox, oy) in offsets {
+            let Resolved::Inside(px, py) =
+                self.boundaries.resolve(blast.x + ox, blast.y + oy, self.width, self.height) else {
+                continue;
+            };
+            let d = (d2 as f64).sqrt();
+            let strength = (255.0 * (1.0 - d / radius as f64)) as u8;
+            let i = self.get_index(px, py);
+            let mut cell = self.cells[i];
+            // 被炸到的火药立即引爆，排到后面炸开，这里当作空气处理
+            if cell.species == Species::Gunpowder {
+                self.blasts.push(Blast { x: px, y: py, radius });
+                cell = EMPTY_CELL;
+            }
+            let toughness = self.registry.toughness(cell.species);
+            if cell.species == Species::Empty || strength > toughness {
+                self.cells[i] = EMPTY_CELL;
+                cleared.push((i, d * 2.0 < radius as f64));
+            } else if toughness < 255 && strength > toughness / 2 && d > 0.0 {
+                let push = 1.0 + (strength - toughness / 2) as f64 / 64.0;
+                let tx = px + (ox as f64 / d * push).round() as i32;
+                let ty = py + (oy as f64 / d * push).round() as i32;
+                if let Resolved::Inside(tx, ty) = self.boundaries.resolve(tx, ty, self.width, self.height) {
+                    let j = self.get_index(tx, ty);
+                    if self.cells[j].species == Species::Empty {
+                        self.cells[j] = cell;
+                        self.cells[i] = EMPTY_CELL;
+                        self.mark_dirty(tx);
+                        self.chunks.wake(tx, ty);
+                    }
+                }
+            }
+
+            self.temps[i] = self.temps[i].max(strength as i16 * 2);
+            self.mark_dirty(px);
+            self.chunks.wake(px, py);
+        }
+
+        for (i, inner) in cleared {
+            // 被推进来的东西占住的格子不放碎屑
+            if self.cells[i].species == Species::Empty {
+                self.cells[i] = self.debris(inner);
+            }
+        }
+    }
+
+    // 被炸毁的格子留下的东西：内圈一半概率是火，外圈一半概率是烟
+    fn debris(&mut self, inner: bool) -> Cell {
+        if !self.rng.gen_bool(0.5) {
+            return EMPTY_CELL;
+        }
+        if inner {
+            Cell {
+                species: Species::Fire,
+                ra: 100 + self.rng.gen_range(0..100),
+                rb: 0,
+                clock: 0,
+            }
+        } else {
+            Cell::new(Species::Smoke, &mut self.rng)
+        }
+    }
+}
@@ -0,0 +1,126 @@
+// 宇宙四条边各自的边界规则，SandApi::get / set 越界时使用：
+//   Wall：越界读到一堵墙，写入被丢弃（原来的行为，默认值）
+//   Void：越界读到空气，写入被丢弃，所以离开地图的细胞会被删除，沙子可以从边缘流走
+//   Wrap：坐标绕到对边，左右都是 Wrap 时地图在水平方向首尾相连（传送带、无限瀑布）
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Boundary {
+    #[default]
+    Wall,
+    Void,
+    Wrap,
+}
+
+impl Boundary {
+    pub fn id(self) -> u8 {
+        match self {
+            Boundary::Wall => 0,
+            Boundary::Void => 1,
+            Boundary::Wrap => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Boundary> {
+        match id {
+            0 => Some(Boundary::Wall),
+            1 => Some(Boundary::Void),
+            2 => Some(Boundary::Wrap),
+            _ => None,
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Boundary> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "wall" => Some(Boundary::Wall),
+            "void" => Some(Boundary::Void),
+            "wrap" => Some(Boundary::Wrap),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Boundaries {
+    pub left: Boundary,
+    pub right: Boundary,
+    pub top: Boundary,
+    pub bottom: Boundary,
+}
+
+// 越界坐标按边界规则解析的结果
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolved {
+    Inside(i32, i32),
+    Wall,
+    Void,
+}
+
+impl Boundaries {
+    pub fn all(boundary: Boundary) -> Self {
+        Self {
+            left: boundary,
+            right: boundary,
+            top: boundary,
+            bottom: boundary,
+        }
+    }
+
+    pub fn wraps(&self) -> bool {
+        [self.left, self.right, self.top, self.bottom].contains(&Boundary::Wrap)
+    }
+
+    // "wrap" 表示四条边相同，"wrap,wrap,wall,void" 依次是左、右、上、下
+    pub fn parse(text: &str) -> Option<Self> {
+        let parts: Vec<&str> = text.split(',').collect();
+        match parts.as_slice() {
+            [all] => Some(Self::all(Boundary::parse(all)?)),
+            [left, right, top, bottom] => Some(Self {
+                left: Boundary::parse(left)?,
+                right: Boundary::parse(right)?,
+                top: Boundary::parse(top)?,
+                bottom: Boundary::parse(bottom)?,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn to_bytes(self) -> [u8; 4] {
+        [self.left.id(), self.right.id(), self.top.id(), self.bottom.id()]
+    }
+
+    pub fn from_bytes(bytes: [u8; 4]) -> Option<Self> {
+        Some(Self {
+            left: Boundary::from_id(bytes[0])?,
+            right: Boundary::from_id(bytes[1])?,
+            top: Boundary::from_id(bytes[2])?,
+            bottom: Boundary::from_id(bytes[3])?,
+        })
+    }
+
+    // 把 (x, y) 解析到 width x height 的网格内；先处理水平方向，再处理竖直方向
+    pub fn resolve(&self, x: i32, y: i32, width: i32, height: i32) -> Resolved {
+        let x = match resolve_axis(x, width, self.left, self.right) {
+            Ok(x) => x,
+            Err(resolved) => return resolved,
+        };
+        match resolve_axis(y, height, self.top, self.bottom) {
+            Ok(y) => Resolved::Inside(x, y),
+            Err(resolved) => resolved,
+        }
+    }
+}
+
+fn resolve_axis(v: i32, len: i32, low: Boundary, high: Boundary) -> Result<i32, Resolved> {
+    let boundary = if v < 0 {
+        low
+    } else if v >= len {
+        high
+    } else {
+        return Ok(v);
+    };
+    match boundary {
+        Boundary::Wall => Err(Resolved::Wall),
+        Boundary::Void => Err(Resolved::Void),
+        Boundary::Wrap => Ok(v.rem_euclid(len)),
+    }
+}
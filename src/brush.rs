@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use crate::replay::not_replaying;
+use crate::species::Species;
+use crate::universe::Universe;
+use crate::world_config::WorldConfig;
+
+// 画笔插件：把鼠标/触摸位置通过相机映射到细胞坐标，然后调用 Universe::paint。
+// 左键（或触摸）使用当前物种绘制，右键擦除；数字键和 P 选择物种，[ ] 调整画笔大小。
+// Ctrl+Z 撤销，Ctrl+Shift+Z（或 Ctrl+Y）重做。
+pub struct BrushPlugin;
+
+const MIN_BRUSH_SIZE: i32 = 1;
+const MAX_BRUSH_SIZE: i32 = 60;
+
+// 数字键 1..9, 0 对应的物种，P 是火药
+const BRUSH_SPECIES: [(KeyCode, Species); 11] = [
+    (KeyCode::Digit1, Species::Sand),
+    (KeyCode::Digit2, Species::Water),
+    (KeyCode::Digit3, Species::Wall),
+    (KeyCode::Digit4, Species::Plant),
+    (KeyCode::Digit5, Species::Fire),
+    (KeyCode::Digit6, Species::Wood),
+    (KeyCode::Digit7, Species::Ice),
+    (KeyCode::Digit8, Species::Lava),
+    (KeyCode::Digit9, Species::Gas),
+    (KeyCode::Digit0, Species::Acid),
+    (KeyCode::KeyP, Species::Gunpowder),
+];
+
+#[derive(Resource)]
+pub struct Brush {
+    pub species: Species,
+    pub size: i32,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self {
+            species: Species::Sand,
+            size: 10,
+        }
+    }
+}
+
+// 当前笔画的状态，last 是上一帧画到的细胞坐标，用于在两帧之间插值
+#[derive(Resource, Default)]
+pub struct BrushStroke {
+    pub last: Option<(i32, i32)>,
+}
+
+impl Plugin for BrushPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Brush>()
+            .init_resource::<BrushStroke>()
+            .add_systems(Update, (
+                brush_select_system,
+                brush_paint_system.after(brush_select_system),
+                undo_system,
+            ).run_if(not_replaying));
+    }
+}
+
+fn brush_select_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut brush: ResMut<Brush>,
+) {
+    for (key, species) in BRUSH_SPECIES.iter() {
+        if keys.just_pressed(*key) {
+            brush.species = *species;
+            println!("brush species: {:?}", brush.species);
+        }
+    }
+    if keys.just_pressed(KeyCode::BracketRight) {
+        brush.size = (brush.size + 2).min(MAX_BRUSH_SIZE);
+        println!("brush size: {}", brush.size);
+    }
+    if keys.just_pressed(KeyCode::BracketLeft) {
+        brush.size = (brush.size - 2).max(MIN_BRUSH_SIZE);
+        println!("brush size: {}", brush.size);
+    }
+}
+
+fn undo_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    stroke: Res<BrushStroke>,
+    mut universe: ResMut<Universe>,
+) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight, KeyCode::SuperLeft, KeyCode::SuperRight]);
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    // 笔画进行中不处理撤销，否则会和笔画开始时保存的快照交错
+    if !ctrl || stroke.last.is_some() {
+        return;
+    }
+    if keys.just_pressed(KeyCode::KeyZ) {
+        if shift {
+            universe.redo();
+        } else {
+            universe.pop_undo();
+        }
+    } else if keys.just_pressed(KeyCode::KeyY) {
+        universe.redo();
+    }
+}
+
+fn brush_paint_system(
+    mouse: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    brush: Res<Brush>,
+    config: Res<WorldConfig>,
+    mut stroke: ResMut<BrushStroke>,
+    mut universe: ResMut<Universe>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+
+    // 触摸优先于鼠标，右键为擦除
+    let (screen_pos, species) = if let Some(touch) = touches.iter().next() {
+        (Some(touch.position()), brush.species)
+    } else if mouse.pressed(MouseButton::Left) {
+        (window.cursor_position(), brush.species)
+    } else if mouse.pressed(MouseButton::Right) {
+        (window.cursor_position(), Species::Empty)
+    } else {
+        (None, brush.species)
+    };
+
+    let Some(screen_pos) = screen_pos else {
+        stroke.last = None;
+        return;
+    };
+    let Some(world_pos) = camera.viewport_to_world_2d(camera_transform, screen_pos) else {
+        return;
+    };
+    let (x, y) = world_to_cell(&universe, config.cell_scale, world_pos);
+
+    match stroke.last {
+        None => {
+            // 新笔画开始时只保存一次撤销状态
+            universe.push_undo();
+            universe.paint(x, y, brush.size, species);
+        }
+        Some((lx, ly)) => {
+            if (lx, ly) == (x, y) {
+                universe.paint(x, y, brush.size, species);
+            } else {
+                paint_line(&mut universe, (lx, ly), (x, y), brush.size, species);
+            }
+        }
+    }
+    stroke.last = Some((x, y));
+}
+
+// 世界坐标 -> 细胞坐标，宇宙以原点为中心，y 轴向下；cell_scale 来自 WorldConfig
+pub fn world_to_cell(universe: &Universe, cell_scale: f32, world_pos: Vec2) -> (i32, i32) {
+    let x = (world_pos.x / cell_scale + universe.width as f32 / 2.0).floor() as i32;
+    let y = (universe.height as f32 / 2.0 - world_pos.y / cell_scale).floor() as i32;
+    (x, y)
+}
+
+// 在两点之间按画笔半径的步长插值，避免快速拖动时出现断点
+fn paint_line(universe: &mut Universe, from: (i32, i32), to: (i32, i32), size: i32, species: Species) {
+    let dx = (to.0 - from.0) as f32;
+    let dy = (to.1 - from.1) as f32;
+    let step = (size as f32 / 2.0).max(1.0);
+    let steps = (dx.abs().max(dy.abs()) / step).ceil().max(1.0) as i32;
+    for i in 1..=steps {
+        let t = i as f32 / steps as f32;
+        let x = from.0 + (dx * t).round() as i32;
+        let y = from.1 + (dy * t).round() as i32;
+        universe.paint(x, y, size, species);
+    }
+}
@@ -0,0 +1,116 @@
+// 分块休眠：把宇宙切成 CHUNK_SIZE x CHUNK_SIZE 的块，每块记录一个休眠倒计时。
+// SandApi::set 真正改变了细胞（物种、ra 或 rb 不同）时唤醒所在的块，倒计时归零的块进入休眠。
+// tick 只处理醒着的块以及与它们相邻的块，已经沉积下来的大片区域几乎不再消耗时间。
+//
+// 有些物种即使这一 tick 没有改变任何东西，之后也会随机发生变化（植物生长、火焰等），
+// 注册表中 always_active 的物种会让所在的块一直保持清醒。
+
+use crate::boundary::{Boundaries, Boundary};
+
+pub const CHUNK_SIZE: i32 = 32;
+
+// 连续这么多个 tick 没有变化后进入休眠。
+// 随机方向的规则（沙子往左还是往右滑）可能几次都没选中可走的方向，倒计时要足够长。
+pub const CHUNK_SLEEP_TICKS: u8 = 32;
+
+#[derive(Clone, Debug)]
+pub struct ChunkMap {
+    cols: i32,
+    rows: i32,
+    // 每块剩余的清醒 tick 数，0 表示休眠
+    timers: Vec<u8>,
+    // 本 tick 需要处理的块（醒着的块及其邻居），在 tick 开始时计算
+    active: Vec<bool>,
+}
+
+impl ChunkMap {
+    pub fn new(width: i32, height: i32) -> Self {
+        let cols = (width + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        let rows = (height + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        let count = (cols * rows).max(0) as usize;
+        Self {
+            cols,
+            rows,
+            timers: vec![CHUNK_SLEEP_TICKS; count],
+            active: vec![true; count],
+        }
+    }
+
+    pub fn cols(&self) -> i32 {
+        self.cols
+    }
+
+    pub fn rows(&self) -> i32 {
+        self.rows
+    }
+
+    fn index(&self, cx: i32, cy: i32) -> usize {
+        (cx * self.rows + cy) as usize
+    }
+
+    // 唤醒包含细胞 (x, y) 的块
+    pub fn wake(&mut self, x: i32, y: i32) {
+        let i = self.index(x / CHUNK_SIZE, y / CHUNK_SIZE);
+        self.timers[i] = CHUNK_SLEEP_TICKS;
+    }
+
+    pub fn wake_all(&mut self) {
+        self.timers.fill(CHUNK_SLEEP_TICKS);
+    }
+
+    pub fn is_awake(&self, cx: i32, cy: i32) -> bool {
+        self.timers[self.index(cx, cy)] > 0
+    }
+
+    pub fn awake_count(&self) -> usize {
+        self.timers.iter().filter(|&&timer| timer > 0).count()
+    }
+
+    // tick 开始时调用：醒着的块和它的 8 个邻居本 tick 都要处理。
+    // 某个方向的边界是 Wrap 时，对边的块也算邻居（细胞会读写到对边去）
+    pub fn begin_tick(&mut self, boundaries: &Boundaries) {
+        let wrap_x = boundaries.left == Boundary::Wrap || boundaries.right == Boundary::Wrap;
+        let wrap_y = boundaries.top == Boundary::Wrap || boundaries.bottom == Boundary::Wrap;
+        for cx in 0..self.cols {
+            for cy in 0..self.rows {
+                let mut active = false;
+                for nx in cx - 1..=cx + 1 {
+                    for ny in cy - 1..=cy + 1 {
+                        let Some(nx) = neighbour(nx, self.cols, wrap_x) else {
+                            continue;
+                        };
+                        let Some(ny) = neighbour(ny, self.rows, wrap_y) else {
+                            continue;
+                        };
+                        active |= self.is_awake(nx, ny);
+                    }
+                }
+                let i = self.index(cx, cy);
+                self.active[i] = active;
+            }
+        }
+    }
+
+    // 本 tick 是否处理块 (cx, cy)
+    pub fn is_active(&self, cx: i32, cy: i32) -> bool {
+        self.active[self.index(cx, cy)]
+    }
+
+    // tick 结束时调用：所有倒计时减一
+    pub fn end_tick(&mut self) {
+        for timer in self.timers.iter_mut() {
+            *timer = timer.saturating_sub(1);
+        }
+    }
+}
+
+// 越界的邻居块：绕回对边，或者不存在
+fn neighbour(c: i32, count: i32, wrap: bool) -> Option<i32> {
+    if (0..count).contains(&c) {
+        Some(c)
+    } else if wrap {
+        Some(c.rem_euclid(count))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,236 @@
+use std::path::PathBuf;
+use bevy::prelude::Resource;
+use crate::boundary::Boundaries;
+use crate::custom_material::load_material_into;
+use crate::emitter::Emitter;
+use crate::parallel::{default_threads, TickSchedule};
+use crate::registry::SpeciesRegistry;
+use crate::universe::{Universe, DEFAULT_SEED};
+use crate::world_config::WorldConfig;
+use crate::world_file::MAX_WORLD_CELLS;
+
+// 默认的存档路径
+pub const DEFAULT_WORLD_PATH: &str = "world.sand";
+// 无头模式默认执行的 tick 次数
+pub const DEFAULT_HEADLESS_TICKS: u64 = 1000;
+
+// 启动参数：
+//   --load <path>   启动时加载存档（.png 按图片格式读取）
+//   --save <path>   Ctrl+S / Ctrl+O 使用的存档路径（默认 world.sand）
+//   --stamp <path>  启动时把 PNG/JPEG 图片转换成细胞铺满宇宙，之后按 T 盖章
+//   --svg <path>    启动时把 SVG 关卡光栅化后导入宇宙（默认铺满）
+//   --svg-rect x,y,w,h  SVG 导入的位置和分辨率
+//   --svg-legend <legend>  按图例匹配填充色，而不是色相分桶：registry 使用注册表的物种代表色，
+//                  或者逗号分隔的 rrggbb=物种名，例如 ff0000=Lava,0000ff=Water（格式见 ColorMapping::parse_legend）
+//   --size <w>x<h>  宇宙的宽和高（默认 300x300），例如 --size 1200x300 的横向地图
+//   --scale <f>     一个细胞在屏幕上的大小
+//   --boundary <mode>  边界规则 wall / void / wrap，或者 左,右,上,下 四个值，例如 wrap,wrap,wall,void
+//   --emitter <spec>  添加一个发射器或排水口，可以重复，例如 water:0.2@top:140:20、drain@bottom（格式见 Emitter::parse）；
+//                  物种名在加载 --material 之后才按注册表解析，所以可以写 X 的自定义名字
+//   --material <path>  自定义材质 X 的配置文件（格式见 custom_material.rs），窗口模式下按 F5 重新加载
+//   --seed <n>      随机数种子（十进制或 0x 开头的十六进制），同一种子加同样的输入可以复现运行
+//   --record <path> 启动后开始录制操作，Ctrl+R 停止或退出时保存（Ctrl+R 默认保存到 session.replay）
+//   --replay <path> 回放录像；无头模式下回放到结束
+//   --threads <n>   使用 n 个线程的棋盘式分块 tick（0 表示按 CPU 核数），结果与线程数无关；默认单线程串行扫描
+//   --deterministic  确定性模式：单线程也按棋盘式分块执行，随机数由种子和细胞位置决定，
+//                  结果与任意 --threads 逐位相同（见 parallel.rs）；使用 --threads 时总是确定性的
+//   --headless      不创建窗口和渲染器，只在命令行里推进模拟（见 headless.rs）
+//   --ticks <n>     无头模式下执行的 tick 次数（默认 1000）
+//   --out <path>    无头模式结束后保存世界的路径（.png 按图片格式）
+//   --stats-every <n>  无头模式下每 n 个 tick 打印一次统计
+//   --bench         以无头模式从同一个起始世界分别用整图串行、确定性串行和棋盘式分块（--threads，默认按 CPU 核数）
+//                  跑 --ticks 个 tick，打印速度，并检查后两者的校验和是否一致（不一致时以状态码 1 退出）
+#[derive(Resource, Clone, Debug)]
+pub struct CliArgs {
+    pub load: Option<PathBuf>,
+    pub save_path: PathBuf,
+    pub stamp: Option<PathBuf>,
+    pub svg: Option<PathBuf>,
+    pub svg_rect: Option<(i32, i32, u32, u32)>,
+    pub svg_legend: Option<String>,
+    pub world: WorldConfig,
+    pub boundaries: Option<Boundaries>,
+    pub emitters: Vec<String>,
+    pub material: Option<PathBuf>,
+    pub seed: u64,
+    pub schedule: TickSchedule,
+    pub deterministic: bool,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub headless: bool,
+    pub ticks: u64,
+    pub out: Option<PathBuf>,
+    pub stats_every: Option<u64>,
+    pub bench: bool,
+}
+
+impl Default for CliArgs {
+    fn default() -> Self {
+        Self {
+            load: None,
+            save_path: PathBuf::from(DEFAULT_WORLD_PATH),
+            stamp: None,
+            svg: None,
+            svg_rect: None,
+            svg_legend: None,
+            world: WorldConfig::default(),
+            boundaries: None,
+            emitters: Vec::new(),
+            material: None,
+            seed: DEFAULT_SEED,
+            schedule: TickSchedule::Serial,
+            deterministic: false,
+            record: None,
+            replay: None,
+            headless: false,
+            ticks: DEFAULT_HEADLESS_TICKS,
+            out: None,
+            stats_every: None,
+            bench: false,
+        }
+    }
+}
+
+impl CliArgs {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Self {
+        let mut cli = CliArgs::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--load" => cli.load = args.next().map(PathBuf::from),
+                "--stamp" => cli.stamp = args.next().map(PathBuf::from),
+                "--svg" => cli.svg = args.next().map(PathBuf::from),
+                "--svg-legend" => cli.svg_legend = args.next(),
+                "--svg-rect" => {
+                    cli.svg_rect = args.next().and_then(|rect| parse_rect(&rect));
+                    if cli.svg_rect.is_none() {
+                        eprintln!("--svg-rect expects x,y,width,height");
+                    }
+                }
+                "--seed" => match args.next().and_then(|n| parse_seed(&n)) {
+                    Some(seed) => cli.seed = seed,
+                    None => eprintln!("--seed expects a number"),
+                },
+                "--size" => match args.next().and_then(|size| parse_size(&size)) {
+                    Some((width, height)) => {
+                        cli.world.width = width;
+                        cli.world.height = height;
+                    }
+                    None => eprintln!("--size expects <width>x<height> with at most {} cells", MAX_WORLD_CELLS),
+                },
+                "--scale" => match args.next().and_then(|scale| scale.parse::<f32>().ok()) {
+                    Some(scale) if scale > 0.0 => cli.world.cell_scale = scale,
+                    _ => eprintln!("--scale expects a positive number"),
+                },
+                "--boundary" => match args.next().and_then(|text| Boundaries::parse(&text)) {
+                    Some(boundaries) => cli.boundaries = Some(boundaries),
+                    None => eprintln!("--boundary expects wall, void, wrap or left,right,top,bottom"),
+                },
+                "--emitter" => match args.next() {
+                    Some(text) => cli.emitters.push(text),
+                    None => eprintln!("--emitter expects <species>:<rate>@<area> or drain@<area>"),
+                },
+                "--material" => cli.material = args.next().map(PathBuf::from),
+                "--threads" => match args.next().and_then(|n| n.parse::<usize>().ok()) {
+                    Some(0) => cli.schedule = TickSchedule::Checkerboard { threads: default_threads() },
+                    Some(threads) => cli.schedule = TickSchedule::Checkerboard { threads },
+                    None => eprintln!("--threads expects a number"),
+                },
+                "--deterministic" => cli.deterministic = true,
+                "--record" => cli.record = args.next().map(PathBuf::from),
+                "--replay" => cli.replay = args.next().map(PathBuf::from),
+                "--headless" => cli.headless = true,
+                "--bench" => cli.bench = true,
+                "--out" => cli.out = args.next().map(PathBuf::from),
+                "--ticks" => match args.next().and_then(|n| n.parse().ok()) {
+                    Some(ticks) => cli.ticks = ticks,
+                    None => eprintln!("--ticks expects a number"),
+                },
+                "--stats-every" => match args.next().and_then(|n| n.parse().ok()) {
+                    Some(0) | None => eprintln!("--stats-every expects a positive number"),
+                    Some(every) => cli.stats_every = Some(every),
+                },
+                "--save" => {
+                    if let Some(path) = args.next() {
+                        cli.save_path = PathBuf::from(path);
+                    }
+                }
+                _ => eprintln!("unknown argument: {}", arg),
+            }
+        }
+        cli
+    }
+}
+
+impl CliArgs {
+    // 按 --size / --seed / --threads / --deterministic / --boundary / --emitter / --material 创建启动时的演示宇宙
+    pub fn build_universe(&self) -> Universe {
+        let mut universe = Universe::demo_scene(self.world.width as i32, self.world.height as i32, self.seed);
+        universe.schedule = self.schedule;
+        universe.deterministic = self.deterministic;
+        universe.boundaries = self.boundaries.unwrap_or_default();
+        if let Some(path) = self.material.as_ref() {
+            let mut registry = SpeciesRegistry::default();
+            load_material_into(&mut registry, path);
+            universe.set_registry(registry);
+        }
+        universe.emitters = self.parse_emitters(&universe.registry);
+        universe
+    }
+
+    // 按注册表解析 --emitter，解析不了的打印出来并跳过
+    fn parse_emitters(&self, registry: &SpeciesRegistry) -> Vec<Emitter> {
+        self.emitters
+            .iter()
+            .filter_map(|text| {
+                let emitter = Emitter::parse(text, registry);
+                if emitter.is_none() {
+                    eprintln!("--emitter {}: expects <species>:<rate>@<area> or drain@<area>", text);
+                }
+                emitter
+            })
+            .collect()
+    }
+}
+
+fn parse_size(text: &str) -> Option<(u32, u32)> {
+    let (width, height) = text.split_once(|c| c == 'x' || c == 'X')?;
+    let width: u32 = width.trim().parse().ok()?;
+    let height: u32 = height.trim().parse().ok()?;
+    if !WorldConfig::valid_size(width, height) {
+        return None;
+    }
+    Some((width, height))
+}
+
+fn parse_seed(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn parse_rect(text: &str) -> Option<(i32, i32, u32, u32)> {
+    let parts: Vec<&str> = text.split(',').map(str::trim).collect();
+    if parts.len() != 4 {
+        return None;
+    }
+    Some((
+        parts[0].parse().ok()?,
+        parts[1].parse().ok()?,
+        parts[2].parse().ok()?,
+        parts[3].parse().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_size_rejects_worlds_over_the_cell_limit() {
+        assert_eq!(parse_size("1200x300"), Some((1200, 300)));
+        assert_eq!(parse_size("0x300"), None);
+        assert_eq!(parse_size("65536x65536"), None);
+        assert_eq!(parse_size("2147483647x2"), None);
+    }
+}
@@ -1,6 +1,159 @@
+use std::fmt;
+use image::RgbaImage;
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{Options, ShapeRendering, Tree};
+use crate::registry::SpeciesRegistry;
 use crate::species::Species;
+use crate::universe::Universe;
 
-pub fn rgba_to_species(r: u8, g: u8, b: u8, a: u8) -> u8 {
+// SVG 关卡导入：把矢量图光栅化到指定分辨率，再把每个像素的填充色映射成物种，写入 Universe 的指定位置。
+// 光栅化时关闭抗锯齿（crispEdges），否则边缘的混合色会被分到别的物种里。
+
+#[derive(Debug)]
+pub enum SvgError {
+    Parse(resvg::usvg::Error),
+    BadSize,
+}
+
+impl fmt::Display for SvgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SvgError::Parse(err) => write!(f, "svg parse error: {}", err),
+            SvgError::BadSize => write!(f, "svg target size must be non-zero"),
+        }
+    }
+}
+
+// 填充色到物种的映射方式
+#[derive(Clone, Debug)]
+pub enum ColorMapping {
+    // 使用 rgba_to_species 的色相/亮度分桶
+    Classify,
+    // 显式图例：颜色距离在 tolerance 以内的取最近的一项，找不到时退回 rgba_to_species
+    Legend {
+        entries: Vec<([u8; 3], Species)>,
+        tolerance: u32,
+    },
+}
+
+impl ColorMapping {
+    // 用注册表里每个物种的代表色生成图例
+    pub fn legend_from_registry(registry: &SpeciesRegistry, tolerance: u32) -> Self {
+        let entries = registry
+            .iter()
+            .filter(|def| def.species != Species::Unknown)
+            .map(|def| ([def.color[0], def.color[1], def.color[2]], def.species))
+            .collect();
+        ColorMapping::Legend { entries, tolerance }
+    }
+
+    // 解析 --svg-legend：registry 表示用注册表的代表色，否则是逗号分隔的 rrggbb=物种名，
+    // 例如 ff0000=Lava,0000ff=Water（颜色前可以带 #，物种名按注册表查找，不区分大小写）
+    pub fn parse_legend(text: &str, registry: &SpeciesRegistry, tolerance: u32) -> Option<Self> {
+        if text.trim().eq_ignore_ascii_case("registry") {
+            return Some(Self::legend_from_registry(registry, tolerance));
+        }
+        let mut entries = Vec::new();
+        for entry in text.split(',') {
+            let (color, name) = entry.split_once('=')?;
+            let color = color.trim();
+            let color = color.strip_prefix('#').unwrap_or(color);
+            if color.len() != 6 {
+                return None;
+            }
+            let rgb = u32::from_str_radix(color, 16).ok()?;
+            let species = registry.species_named(name.trim())?;
+            entries.push(([(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8], species));
+        }
+        Some(ColorMapping::Legend { entries, tolerance })
+    }
+
+    pub fn species(&self, registry: &SpeciesRegistry, r: u8, g: u8, b: u8, a: u8) -> Species {
+        if let ColorMapping::Legend { entries, tolerance } = self {
+            let nearest = entries
+                .iter()
+                .map(|(color, species)| (color_distance(*color, [r, g, b]), *species))
+                .min_by_key(|(distance, _)| *distance);
+            if let Some((distance, species)) = nearest {
+                if distance <= tolerance * tolerance {
+                    return species;
+                }
+            }
+        }
+        Species::from_id(rgba_to_species(registry, r, g, b, a))
+    }
+}
+
+fn color_distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(&x, &y)| (x as i32 - y as i32).pow(2) as u32)
+        .sum()
+}
+
+// 导入参数：光栅化的分辨率（细胞数），以及左上角在宇宙中的位置
+#[derive(Clone, Debug)]
+pub struct SvgImport {
+    pub width: u32,
+    pub height: u32,
+    pub offset_x: i32,
+    pub offset_y: i32,
+    pub mapping: ColorMapping,
+}
+
+impl SvgImport {
+    // 铺满整个宇宙
+    pub fn fit_universe(universe: &Universe) -> Self {
+        Self {
+            width: universe.width as u32,
+            height: universe.height as u32,
+            offset_x: 0,
+            offset_y: 0,
+            mapping: ColorMapping::Classify,
+        }
+    }
+}
+
+// 把 SVG 光栅化成 width x height 的图片（非预乘 RGBA）
+pub fn rasterize_svg(data: &[u8], width: u32, height: u32) -> Result<RgbaImage, SvgError> {
+    let options = Options {
+        shape_rendering: ShapeRendering::CrispEdges,
+        ..Options::default()
+    };
+    let tree = Tree::from_data(data, &options).map_err(SvgError::Parse)?;
+    let mut pixmap = Pixmap::new(width, height).ok_or(SvgError::BadSize)?;
+
+    let size = tree.size();
+    let transform = Transform::from_scale(width as f32 / size.width(), height as f32 / size.height());
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    let mut image = RgbaImage::new(width, height);
+    for (pixel, color) in image.pixels_mut().zip(pixmap.pixels()) {
+        let color = color.demultiply();
+        pixel.0 = [color.red(), color.green(), color.blue(), color.alpha()];
+    }
+    Ok(image)
+}
+
+pub fn import_svg(universe: &mut Universe, data: &[u8], import: &SvgImport) -> Result<(), SvgError> {
+    let image = rasterize_svg(data, import.width, import.height)?;
+    let registry = universe.registry.clone();
+    for (px, py, pixel) in image.enumerate_pixels() {
+        let [r, g, b, a] = pixel.0;
+        // 透明区域不覆盖原有内容
+        if a < 250 {
+            continue;
+        }
+        let species = import.mapping.species(&registry, r, g, b, a);
+        universe.place(import.offset_x + px as i32, import.offset_y + py as i32, species);
+    }
+    Ok(())
+}
+
+// 颜色分桶到物种的映射登记在 SpeciesRegistry 的 import_buckets 中。
+// 亮度桶 3（很浅的颜色）原来全部导入为 Empty，现在浅蓝 (4, 3) 导入为 Snow，浅青 (3, 3) 导入为 Steam，
+// 其余色相仍是 Empty。旧图章和 SVG 里的这两种浅色会变成雪和蒸汽
+pub fn rgba_to_species(registry: &SpeciesRegistry, r: u8, g: u8, b: u8, a: u8) -> u8 {
     // 透明时返回 Empty
     if a < 250 {
         return Species::Empty as u8;
@@ -56,19 +209,22 @@ pub fn rgba_to_species(r: u8, g: u8, b: u8, a: u8) -> u8 {
     let hue_index = ((h + 25.7) / 360.0 * 7.0).floor() as usize;
     let lightness_index = (l * 4.0 - 0.25).floor() as usize;
 
-    let colors_to_species: Vec<Vec<Option<Species>>> = vec![
-        vec![Some(Species::Fire), Some(Species::Lava), Some(Species::Rocket)], // Red
-        vec![Some(Species::Wood), None, Some(Species::Gas)], // Yellow
-        vec![Some(Species::Plant), Some(Species::Dust), Some(Species::Acid)], // Green
-        vec![Some(Species::Plant), Some(Species::Dust), Some(Species::Acid)], // Green2
-        vec![Some(Species::Water), Some(Species::Ice), Some(Species::Stone)], // Blue
-        vec![Some(Species::Oil), Some(Species::Seed), Some(Species::Fungus)], // Purple
-        vec![Some(Species::Cloner), Some(Species::Mite), None], // Violet
-    ];
-
-    if let Some(species) = colors_to_species.get(hue_index).and_then(|x| x.get(lightness_index)) {
-        return species.map_or(Species::Empty as u8, |s| s as u8);
-    }
+    registry
+        .species_for_bucket(hue_index, lightness_index)
+        .map_or(Species::Empty as u8, |s| s as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Species::Empty as u8
-}
\ No newline at end of file
+    #[test]
+    fn pale_colors_import_as_snow_steam_or_empty() {
+        let registry = SpeciesRegistry::default();
+        assert_eq!(rgba_to_species(&registry, 225, 235, 250, 255), Species::Snow as u8);
+        assert_eq!(rgba_to_species(&registry, 220, 250, 245, 255), Species::Steam as u8);
+        // 浅黄色以前会变成闪电
+        assert_eq!(rgba_to_species(&registry, 255, 252, 180, 255), Species::Empty as u8);
+        assert_eq!(rgba_to_species(&registry, 255, 255, 255, 255), Species::Empty as u8);
+    }
+}
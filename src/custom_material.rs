@@ -0,0 +1,201 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use bevy::prelude::*;
+use crate::cli::CliArgs;
+use crate::registry::SpeciesRegistry;
+use crate::replay::not_replaying;
+use crate::world_file::{ByteReader, WorldFileError};
+
+// 自定义材质：物种 X（id 21）的行为参数来自一个手写的配置文件，改完按 F5 重新加载，不需要重新编译。
+// 配置文件每行一个 key = value，# 之后是注释，没写的项使用默认值：
+//
+//   name = Slime
+//   gravity = 1                 # 1 下落，-1 上浮，0 静止
+//   liquidity = 0.8             # 0..1，落不下去时向两侧流动的概率，0 像沙子一样堆积
+//   flammability = 0.05         # 0..1，每个 tick 碰到火或岩浆时着火的概率
+//   corrosion_resistance = 0.5  # 0..1，被酸腐蚀时抵抗住的概率
+//   color = 120, 200, 80        # 代表色，渲染、导出预览和图例都用它
+//   wind_threshold = 40
+//   density = 100               # 0..255，决定它能沉入（或浮出）哪些液体
+//   toughness = 50              # 0..255，抗爆程度，255 炸不坏也推不动
+//
+// 录像在开头和每次重新加载时保存材质的参数（encode_material），回放不需要配置文件。
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CustomMaterial {
+    pub name: String,
+    pub gravity: i32,
+    pub liquidity: f64,
+    pub flammability: f64,
+    pub corrosion_resistance: f64,
+    pub color: [u8; 4],
+    pub wind_threshold: i32,
+    pub density: u8,
+    pub toughness: u8,
+}
+
+impl Default for CustomMaterial {
+    fn default() -> Self {
+        Self {
+            name: "X".into(),
+            gravity: 1,
+            liquidity: 0.5,
+            flammability: 0.0,
+            corrosion_resistance: 0.0,
+            color: [150, 150, 220, 255],
+            wind_threshold: 40,
+            density: 100,
+            toughness: 50,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MaterialError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for MaterialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaterialError::Io(err) => write!(f, "io error: {}", err),
+            MaterialError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl From<io::Error> for MaterialError {
+    fn from(err: io::Error) -> Self {
+        MaterialError::Io(err)
+    }
+}
+
+impl CustomMaterial {
+    pub fn parse(text: &str) -> Result<Self, MaterialError> {
+        let mut material = CustomMaterial::default();
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let error = |message: String| MaterialError::Parse { line: line_no, message };
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected key = value".into()))?;
+            let key = key.trim();
+            let value = value.trim();
+            let bad_value = || error(format!("bad value for {}: {}", key, value));
+            match key {
+                "name" => {
+                    if value.is_empty() {
+                        return Err(bad_value());
+                    }
+                    material.name = value.to_string();
+                }
+                "gravity" => material.gravity = value.parse::<i32>().map_err(|_| bad_value())?.clamp(-1, 1),
+                "liquidity" => material.liquidity = parse_chance(value).ok_or_else(bad_value)?,
+                "flammability" => material.flammability = parse_chance(value).ok_or_else(bad_value)?,
+                "corrosion_resistance" => material.corrosion_resistance = parse_chance(value).ok_or_else(bad_value)?,
+                "color" => material.color = parse_color(value).ok_or_else(bad_value)?,
+                "wind_threshold" => material.wind_threshold = value.parse().map_err(|_| bad_value())?,
+                "density" => material.density = value.parse().map_err(|_| bad_value())?,
+                "toughness" => material.toughness = value.parse().map_err(|_| bad_value())?,
+                _ => return Err(error(format!("unknown key {}", key))),
+            }
+        }
+        Ok(material)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, MaterialError> {
+        CustomMaterial::parse(&fs::read_to_string(path)?)
+    }
+}
+
+fn parse_chance(text: &str) -> Option<f64> {
+    text.parse::<f64>().ok().filter(|v| (0.0..=1.0).contains(v))
+}
+
+// "r, g, b" 或 "r, g, b, a"
+fn parse_color(text: &str) -> Option<[u8; 4]> {
+    let parts: Vec<u8> = text.split(',').map(|v| v.trim().parse().ok()).collect::<Option<_>>()?;
+    match parts.as_slice() {
+        &[r, g, b] => Some([r, g, b, 255]),
+        &[r, g, b, a] => Some([r, g, b, a]),
+        _ => None,
+    }
+}
+
+// 录像里的材质（小端）：名字长度 u32 + UTF-8 名字，gravity i32，liquidity / flammability / corrosion_resistance 各 f64，
+// color 4 字节，wind_threshold i32，density u8，toughness u8
+pub fn encode_material(out: &mut Vec<u8>, material: &CustomMaterial) {
+    out.extend_from_slice(&(material.name.len() as u32).to_le_bytes());
+    out.extend_from_slice(material.name.as_bytes());
+    out.extend_from_slice(&material.gravity.to_le_bytes());
+    for chance in [material.liquidity, material.flammability, material.corrosion_resistance] {
+        out.extend_from_slice(&chance.to_bits().to_le_bytes());
+    }
+    out.extend_from_slice(&material.color);
+    out.extend_from_slice(&material.wind_threshold.to_le_bytes());
+    out.push(material.density);
+    out.push(material.toughness);
+}
+
+pub fn decode_material(reader: &mut ByteReader) -> Result<CustomMaterial, WorldFileError> {
+    let name_len = reader.u32()? as usize;
+    let name = String::from_utf8(reader.take(name_len)?.to_vec())
+        .map_err(|_| WorldFileError::Corrupt("material name is not UTF-8"))?;
+    let gravity = reader.i32()?;
+    let liquidity = f64::from_bits(reader.u64()?);
+    let flammability = f64::from_bits(reader.u64()?);
+    let corrosion_resistance = f64::from_bits(reader.u64()?);
+    let c = reader.take(4)?;
+    Ok(CustomMaterial {
+        name,
+        gravity,
+        liquidity,
+        flammability,
+        corrosion_resistance,
+        color: [c[0], c[1], c[2], c[3]],
+        wind_threshold: reader.i32()?,
+        density: reader.u8()?,
+        toughness: reader.u8()?,
+    })
+}
+
+// 注册表里使用 --material 指定的材质，读取失败时保留默认材质
+pub fn load_material_into(registry: &mut SpeciesRegistry, path: &Path) {
+    match CustomMaterial::load(path) {
+        Ok(material) => {
+            println!("loaded material {} from {}", material.name, path.display());
+            registry.set_custom_material(material);
+        }
+        Err(err) => eprintln!("failed to load material {}: {}", path.display(), err),
+    }
+}
+
+// F5 重新加载 --material 指定的配置文件，SpeciesRegistry 变化后由 SimulationPlugin 同步到 Universe
+pub struct CustomMaterialPlugin;
+
+impl Plugin for CustomMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, reload_material_system.run_if(not_replaying));
+    }
+}
+
+fn reload_material_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    args: Res<CliArgs>,
+    mut registry: ResMut<SpeciesRegistry>,
+) {
+    if !keys.just_pressed(KeyCode::F5) {
+        return;
+    }
+    match args.material.as_ref() {
+        Some(path) => load_material_into(&mut registry, path),
+        None => eprintln!("no --material file to reload"),
+    }
+}
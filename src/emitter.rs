@@ -0,0 +1,361 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use rand::Rng;
+use crate::brush::{world_to_cell, Brush};
+use crate::registry::SpeciesRegistry;
+use crate::replay::{not_replaying, ReplayEvent};
+use crate::species::Species;
+use crate::universe::{Cell, Universe, EMPTY_CELL};
+use crate::world_config::WorldConfig;
+use crate::world_file::{ByteReader, WorldFileError};
+
+// 发射器和排水口：宇宙上的矩形或边缘线段，每个 tick 在细胞更新之前处理一次。
+//   Source：区域内的每个空格以 rate 的概率生成一个 species 细胞（水龙头、沙漏的入口）
+//   Drain：删除进入区域的所有细胞，墙除外，方便用墙围出排水口
+// 发射器随存档保存，修改会写进录像；随机数来自宇宙的 rng，与 tick 调度无关。
+
+// 新建水源时的默认速率
+pub const DEFAULT_SOURCE_RATE: f32 = 0.1;
+// 运行时调整速率的范围
+const MIN_SOURCE_RATE: f32 = 1.0 / 1024.0;
+const MAX_SOURCE_RATE: f32 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Side {
+    fn id(self) -> u8 {
+        match self {
+            Side::Left => 0,
+            Side::Right => 1,
+            Side::Top => 2,
+            Side::Bottom => 3,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Side> {
+        match id {
+            0 => Some(Side::Left),
+            1 => Some(Side::Right),
+            2 => Some(Side::Top),
+            3 => Some(Side::Bottom),
+            _ => None,
+        }
+    }
+
+    fn parse(text: &str) -> Option<Side> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "left" => Some(Side::Left),
+            "right" => Some(Side::Right),
+            "top" => Some(Side::Top),
+            "bottom" => Some(Side::Bottom),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmitterArea {
+    Rect { x: i32, y: i32, width: i32, height: i32 },
+    // 贴着某条边、一格厚的线段，start 和 len 沿着这条边计算；宇宙改变大小后仍然贴着这条边
+    Edge { side: Side, start: i32, len: i32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmitterKind {
+    Source { species: Species, rate: f32 },
+    Drain,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Emitter {
+    pub area: EmitterArea,
+    pub kind: EmitterKind,
+}
+
+impl Emitter {
+    // 裁剪到 width x height 宇宙内的区域 (x0, y0, x1, y1)，右下开区间
+    pub fn bounds(&self, width: i32, height: i32) -> (i32, i32, i32, i32) {
+        let (x0, y0, x1, y1) = match self.area {
+            EmitterArea::Rect { x, y, width: w, height: h } => (x, y, x.saturating_add(w), y.saturating_add(h)),
+            EmitterArea::Edge { side, start, len } => {
+                let end = start.saturating_add(len);
+                match side {
+                    Side::Left => (0, start, 1, end),
+                    Side::Right => (width - 1, start, width, end),
+                    Side::Top => (start, 0, end, 1),
+                    Side::Bottom => (start, height - 1, end, height),
+                }
+            }
+        };
+        (x0.max(0), y0.max(0), x1.min(width), y1.min(height))
+    }
+
+    pub fn contains(&self, x: i32, y: i32, width: i32, height: i32) -> bool {
+        let (x0, y0, x1, y1) = self.bounds(width, height);
+        x >= x0 && x < x1 && y >= y0 && y < y1
+    }
+
+    // 命令行格式：<物种>:<速率>@<区域> 或 drain@<区域>，
+    // 区域是 x,y,w,h 的矩形，或者 top / bottom / left / right，可以跟 :start:len 只占边的一段。
+    // 例如 water:0.2@top:140:20、sand:0.05@10,10,4,4、drain@bottom
+    pub fn parse(text: &str, registry: &SpeciesRegistry) -> Option<Emitter> {
+        let (kind, area) = text.split_once('@')?;
+        let kind = if kind.trim().eq_ignore_ascii_case("drain") {
+            EmitterKind::Drain
+        } else {
+            let (name, rate) = kind.split_once(':')?;
+            let rate: f32 = rate.trim().parse().ok()?;
+            if !(rate > 0.0 && rate <= MAX_SOURCE_RATE) {
+                return None;
+            }
+            EmitterKind::Source { species: registry.species_named(name.trim())?, rate }
+        };
+        let area = if area.contains(',') {
+            let parts: Vec<i32> = area.split(',').map(|v| v.trim().parse().ok()).collect::<Option<_>>()?;
+            match parts.as_slice() {
+                &[x, y, width, height] if width > 0 && height > 0 => EmitterArea::Rect { x, y, width, height },
+                _ => return None,
+            }
+        } else {
+            let parts: Vec<&str> = area.split(':').collect();
+            match parts.as_slice() {
+                [side] => EmitterArea::Edge { side: Side::parse(side)?, start: 0, len: i32::MAX },
+                [side, start, len] => EmitterArea::Edge {
+                    side: Side::parse(side)?,
+                    start: start.trim().parse().ok()?,
+                    len: len.trim().parse().ok().filter(|&len: &i32| len > 0)?,
+                },
+                _ => return None,
+            }
+        };
+        Some(Emitter { area, kind })
+    }
+}
+
+// 发射器列表的二进制格式（小端），存档和录像共用：
+//   个数 u32，然后每个发射器：
+//   类型 u8（0 = Source，1 = Drain），Source 接着是物种 u8 和速率 f32
+//   区域 u8（0 = 矩形：x, y, w, h 四个 i32；1 = 边：边 u8，start、len 两个 i32）
+pub fn encode_emitters(out: &mut Vec<u8>, emitters: &[Emitter]) {
+    out.extend_from_slice(&(emitters.len() as u32).to_le_bytes());
+    for emitter in emitters {
+        match emitter.kind {
+            EmitterKind::Source { species, rate } => {
+                out.push(0);
+                out.push(species as u8);
+                out.extend_from_slice(&rate.to_le_bytes());
+            }
+            EmitterKind::Drain => out.push(1),
+        }
+        match emitter.area {
+            EmitterArea::Rect { x, y, width, height } => {
+                out.push(0);
+                for value in [x, y, width, height] {
+                    out.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            EmitterArea::Edge { side, start, len } => {
+                out.push(1);
+                out.push(side.id());
+                out.extend_from_slice(&start.to_le_bytes());
+                out.extend_from_slice(&len.to_le_bytes());
+            }
+        }
+    }
+}
+
+pub fn decode_emitters(reader: &mut ByteReader) -> Result<Vec<Emitter>, WorldFileError> {
+    let count = reader.u32()? as usize;
+    let mut emitters = Vec::with_capacity(count.min(1024));
+    for _ in 0..count {
+        let kind = match reader.u8()? {
+            0 => EmitterKind::Source {
+                species: Species::from_id(reader.u8()?),
+                rate: f32::from_bits(reader.u32()?),
+            },
+            1 => EmitterKind::Drain,
+            _ => return Err(WorldFileError::Corrupt("unknown emitter kind")),
+        };
+        let area = match reader.u8()? {
+            0 => EmitterArea::Rect {
+                x: reader.i32()?,
+                y: reader.i32()?,
+                width: reader.i32()?,
+                height: reader.i32()?,
+            },
+            1 => EmitterArea::Edge {
+                side: Side::from_id(reader.u8()?).ok_or(WorldFileError::Corrupt("unknown emitter side"))?,
+                start: reader.i32()?,
+                len: reader.i32()?,
+            },
+            _ => return Err(WorldFileError::Corrupt("unknown emitter area")),
+        };
+        emitters.push(Emitter { area, kind });
+    }
+    Ok(emitters)
+}
+
+impl Universe {
+    // tick 开始时处理所有发射器，按列表顺序执行
+    pub(crate) fn apply_emitters(&mut self) {
+        for i in 0..self.emitters.len() {
+            let emitter = self.emitters[i];
+            let (x0, y0, x1, y1) = emitter.bounds(self.width, self.height);
+            for x in x0..x1 {
+                for y in y0..y1 {
+                    let idx = self.get_index(x, y);
+                    let old = self.cells[idx];
+                    let new = match emitter.kind {
+                        EmitterKind::Source { species, rate } => {
+                            if old.species != Species::Empty || self.rng.gen::<f32>() >= rate {
+                                continue;
+                            }
+                            Cell {
+                                clock: self.generation,
+                                ..Cell::new(species, &mut self.rng)
+                            }
+                        }
+                        EmitterKind::Drain => {
+                            if old.species == Species::Empty || old.species == Species::Wall {
+                                continue;
+                            }
+                            EMPTY_CELL
+                        }
+                    };
+                    self.cells[idx] = new;
+                    self.mark_dirty(x);
+                    self.chunks.wake(x, y);
+                }
+            }
+        }
+    }
+
+    // 替换整个发射器列表，录制中会写进录像
+    pub fn set_emitters(&mut self, emitters: Vec<Emitter>) {
+        self.record(ReplayEvent::Emitters(emitters.clone()));
+        self.emitters = emitters;
+    }
+
+    pub fn add_emitter(&mut self, emitter: Emitter) {
+        let mut emitters = self.emitters.clone();
+        emitters.push(emitter);
+        self.set_emitters(emitters);
+    }
+
+    pub fn remove_emitter(&mut self, index: usize) {
+        if index < self.emitters.len() {
+            let mut emitters = self.emitters.clone();
+            emitters.remove(index);
+            self.set_emitters(emitters);
+        }
+    }
+
+    // 修改水源的物种和速率，对排水口无效
+    pub fn set_emitter_source(&mut self, index: usize, species: Species, rate: f32) {
+        let Some(Emitter { kind: EmitterKind::Source { .. }, .. }) = self.emitters.get(index) else {
+            return;
+        };
+        let mut emitters = self.emitters.clone();
+        emitters[index].kind = EmitterKind::Source {
+            species,
+            rate: rate.clamp(MIN_SOURCE_RATE, MAX_SOURCE_RATE),
+        };
+        self.set_emitters(emitters);
+    }
+
+    // 包含 (x, y) 的最后一个发射器（后添加的在上面）
+    pub fn emitter_at(&self, x: i32, y: i32) -> Option<usize> {
+        self.emitters
+            .iter()
+            .rposition(|emitter| emitter.contains(x, y, self.width, self.height))
+    }
+}
+
+// 运行时编辑发射器，作用于鼠标所在的位置：
+//   G        在光标处放一个画笔大小的水源，物种为当前画笔物种；光标已经在水源上时改成当前画笔物种
+//   H        在光标处放一个画笔大小的排水口
+//   N / M    把光标处水源的速率减半 / 加倍
+//   X        删除光标处的发射器
+pub struct EmitterPlugin;
+
+impl Plugin for EmitterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, emitter_input_system.run_if(not_replaying));
+    }
+}
+
+fn emitter_input_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    brush: Res<Brush>,
+    config: Res<WorldConfig>,
+    mut universe: ResMut<Universe>,
+) {
+    if !keys.any_just_pressed([KeyCode::KeyG, KeyCode::KeyH, KeyCode::KeyN, KeyCode::KeyM, KeyCode::KeyX]) {
+        return;
+    }
+    // Ctrl 组合键留给存档和录像
+    if keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight, KeyCode::SuperLeft, KeyCode::SuperRight]) {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+    let Some(world_pos) = window
+        .cursor_position()
+        .and_then(|pos| camera.viewport_to_world_2d(camera_transform, pos)) else {
+        return;
+    };
+    let (x, y) = world_to_cell(&universe, config.cell_scale, world_pos);
+    let size = brush.size.max(1);
+    let area = EmitterArea::Rect { x: x - size / 2, y: y - size / 2, width: size, height: size };
+    let under_cursor = universe.emitter_at(x, y);
+    let source = under_cursor.and_then(|i| match universe.emitters[i].kind {
+        EmitterKind::Source { species, rate } => Some((i, species, rate)),
+        EmitterKind::Drain => None,
+    });
+
+    if keys.just_pressed(KeyCode::KeyG) {
+        match source {
+            Some((i, _, rate)) => universe.set_emitter_source(i, brush.species, rate),
+            None => universe.add_emitter(Emitter {
+                area,
+                kind: EmitterKind::Source { species: brush.species, rate: DEFAULT_SOURCE_RATE },
+            }),
+        }
+        println!("source: {:?}", brush.species);
+    }
+    if keys.just_pressed(KeyCode::KeyH) {
+        universe.add_emitter(Emitter { area, kind: EmitterKind::Drain });
+        println!("drain added");
+    }
+    if let Some((i, species, rate)) = source {
+        let rate = if keys.just_pressed(KeyCode::KeyN) {
+            rate / 2.0
+        } else if keys.just_pressed(KeyCode::KeyM) {
+            rate * 2.0
+        } else {
+            rate
+        };
+        if keys.any_just_pressed([KeyCode::KeyN, KeyCode::KeyM]) {
+            universe.set_emitter_source(i, species, rate);
+            println!("source rate: {}", rate.clamp(MIN_SOURCE_RATE, MAX_SOURCE_RATE));
+        }
+    }
+    if keys.just_pressed(KeyCode::KeyX) {
+        if let Some(i) = under_cursor {
+            universe.remove_emitter(i);
+            println!("emitter removed");
+        }
+    }
+}
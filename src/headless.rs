@@ -0,0 +1,193 @@
+use std::path::Path;
+use std::time::Instant;
+use crate::cli::CliArgs;
+use crate::parallel::{default_threads, TickSchedule};
+use crate::replay::{player_from_path, save_replay, start_recording, stop_recording};
+use crate::species::Species;
+use crate::stamp::{import_svg_from_args, stamp_from_args};
+use crate::universe::Universe;
+use crate::world_file::{decode_world, encode_world, load_with_log, replace_universe, save_with_log};
+
+// 无头模式：不创建窗口、不初始化 wgpu，直接在当前线程里推进 Universe。
+// 给回归任务和批量生成场景的脚本使用，在没有 GPU 的 Linux 服务器上也能运行：
+//   sandspiel_bevy --headless --load in.sand --ticks 5000 --out out.png --stats-every 1000
+// --load / --stamp / --svg 的处理顺序与窗口模式的启动系统相同。
+// --replay 时忽略 --ticks，把录像回放到结束；--record 把这次运行录制下来。
+// --bench 比较几种调度的速度，并检查确定性模式的结果是否与调度无关（见 parallel.rs）。
+
+pub fn run(args: &CliArgs) {
+    if let Some(path) = args.replay.as_ref() {
+        run_replay(args, path);
+        return;
+    }
+    if args.bench {
+        run_bench(args);
+        return;
+    }
+
+    let mut universe = start_universe(args);
+    if args.record.is_some() {
+        start_recording(&mut universe);
+    }
+
+    let start = Instant::now();
+    let mut printed = None;
+    for tick in 1..=args.ticks {
+        universe.tick();
+        if args.stats_every.map_or(false, |every| tick % every == 0) {
+            print_stats(&universe, tick);
+            printed = Some(tick);
+        }
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    // 最后一个 tick 没有打印过统计时（包括 --ticks 0）补上最终的统计
+    if printed != Some(args.ticks) {
+        print_stats(&universe, args.ticks);
+    }
+    if elapsed > 0.0 {
+        println!("{} ticks in {:.3}s ({:.1} ticks/s)", args.ticks, elapsed, args.ticks as f64 / elapsed);
+    }
+
+    if let Some(path) = args.out.as_ref() {
+        save_with_log(&universe, path);
+    }
+    if let (Some(path), Some(recorder)) = (args.record.as_ref(), stop_recording(&mut universe)) {
+        match save_replay(&recorder, path) {
+            Ok(()) => println!("saved {} replay events to {}", recorder.len(), path.display()),
+            Err(err) => eprintln!("failed to save replay {}: {}", path.display(), err),
+        }
+    }
+}
+
+// 按 --load / --stamp / --svg 准备起始世界
+fn start_universe(args: &CliArgs) -> Universe {
+    let mut universe = args.build_universe();
+    println!("seed {:#x}", args.seed);
+    if let Some(path) = args.load.as_ref() {
+        // build_universe 已经按注册表解析好了 --emitter
+        let emitters = universe.emitters.clone();
+        load_with_log(&mut universe, path);
+        if let Some(boundaries) = args.boundaries {
+            universe.boundaries = boundaries;
+        }
+        if !emitters.is_empty() {
+            universe.emitters = emitters;
+        }
+    }
+    stamp_from_args(&mut universe, args);
+    import_svg_from_args(&mut universe, args);
+    universe
+}
+
+// 从同一个起始世界（连同 rng 状态）分别用三种调度跑 --ticks 个 tick
+fn run_bench(args: &CliArgs) {
+    let start = encode_world(&start_universe(args));
+    let threads = match args.schedule {
+        TickSchedule::Checkerboard { threads } => threads,
+        TickSchedule::Serial => default_threads(),
+    };
+    let runs = [
+        ("serial".to_string(), TickSchedule::Serial, false),
+        ("deterministic serial".to_string(), TickSchedule::Serial, true),
+        (format!("checkerboard x{}", threads), TickSchedule::Checkerboard { threads }, true),
+    ];
+    let mut checksums = Vec::new();
+    for (name, schedule, deterministic) in runs {
+        let mut universe = args.build_universe();
+        match decode_world(&start) {
+            Ok(world) => replace_universe(&mut universe, world),
+            Err(err) => {
+                eprintln!("failed to copy the start world: {}", err);
+                return;
+            }
+        }
+        universe.schedule = schedule;
+        universe.deterministic = deterministic;
+        let clock = Instant::now();
+        for _ in 0..args.ticks {
+            universe.tick();
+        }
+        let elapsed = clock.elapsed().as_secs_f64().max(f64::EPSILON);
+        let checksum = universe.checksum();
+        println!(
+            "{:<20} {} ticks in {:.3}s ({:.1} ticks/s), checksum {:#018x}",
+            name,
+            args.ticks,
+            elapsed,
+            args.ticks as f64 / elapsed,
+            checksum,
+        );
+        checksums.push(checksum);
+    }
+    if checksums[1] != checksums[2] {
+        eprintln!("deterministic serial and checkerboard x{} differ", threads);
+        std::process::exit(1);
+    }
+    println!("deterministic serial and checkerboard x{} match", threads);
+}
+
+fn run_replay(args: &CliArgs, path: &Path) {
+    let mut universe = args.build_universe();
+    let mut player = match player_from_path(&mut universe, path) {
+        Ok(player) => player,
+        Err(err) => {
+            eprintln!("failed to load replay {}: {}", path.display(), err);
+            return;
+        }
+    };
+    println!("replaying {} (seed {:#x})", path.display(), universe.seed());
+
+    let mut tick = 0;
+    let mut printed = None;
+    loop {
+        match player.step(&mut universe) {
+            Ok(true) => tick += 1,
+            Ok(false) => break,
+            Err(desync) => {
+                eprintln!("{}", desync);
+                break;
+            }
+        }
+        if args.stats_every.map_or(false, |every| tick % every == 0) {
+            print_stats(&universe, tick);
+            printed = Some(tick);
+        }
+    }
+    if printed != Some(tick) {
+        print_stats(&universe, tick);
+    }
+
+    if let Some(path) = args.out.as_ref() {
+        save_with_log(&universe, path);
+    }
+}
+
+// 每个物种的细胞数，按 id 索引
+pub fn species_counts(universe: &Universe) -> [usize; 256] {
+    let mut counts = [0; 256];
+    for cell in universe.cells.iter() {
+        counts[cell.species as u8 as usize] += 1;
+    }
+    counts
+}
+
+fn print_stats(universe: &Universe, tick: u64) {
+    let counts = species_counts(universe);
+    let mut line = format!(
+        "tick {} ({}x{}, awake chunks {}/{}):",
+        tick,
+        universe.width,
+        universe.height,
+        universe.chunks.awake_count(),
+        universe.chunks.cols() * universe.chunks.rows(),
+    );
+    for (id, &count) in counts.iter().enumerate() {
+        if count == 0 {
+            continue;
+        }
+        let species = Species::from_id(id as u8);
+        line.push_str(&format!(" {}={}", universe.registry.name(species), count));
+    }
+    println!("{}", line);
+}
@@ -1,26 +1,53 @@
 #![allow(clippy::type_complexity)]
 extern crate core;
 
+mod blast;
 mod boot;
+mod boundary;
+mod brush;
+mod chunks;
+mod cli;
 mod convert_svg;
+mod custom_material;
+mod emitter;
+mod headless;
 mod species;
 mod universe;
 mod utils;
+mod world_file;
+mod world_image;
+mod world_config;
+mod registry;
 mod render;
+mod replay;
+mod rle;
+mod parallel;
 mod pipeline_c;
 mod pipeline_reset;
 mod pipeline_sand;
 mod pipeline_update;
+mod simulation;
+mod stamp;
+mod temperature;
 
 use bevy::prelude::*;
 use bevy::render::{RenderApp, RenderPlugin};
 use bevy::render::settings::{Backends, WgpuSettings};
 use bevy::sprite::MaterialMesh2dBundle;
 use bevy::window::{PresentMode, WindowResolution};
+use crate::brush::BrushPlugin;
+use crate::cli::CliArgs;
+use crate::custom_material::CustomMaterialPlugin;
+use crate::emitter::EmitterPlugin;
 use crate::pipeline_reset::{ResetPipelinePlugin};
 use crate::pipeline_sand::PipelineSandPlugin;
+use crate::replay::ReplayPlugin;
+use crate::simulation::SimulationPlugin;
+use crate::stamp::StampPlugin;
 use crate::species::Species;
 use crate::universe::Universe;
+use crate::world_config::WorldConfigPlugin;
+use crate::world_file::WorldFilePlugin;
 
 #[derive(Resource)]
 struct FluidConfig {
@@ -41,6 +68,12 @@ pub enum GameOfLifeState {
     Reset
 }
 fn main() {
+    let args = CliArgs::parse(std::env::args().skip(1));
+    if args.headless || args.bench {
+        headless::run(&args);
+        return;
+    }
+    let universe = args.build_universe();
     let mut app = App::new();
 
     app
@@ -78,10 +111,22 @@ fn main() {
             splat_radius: 0.005,
         })
         //local plugins
-        .insert_resource(Universe::default())
+        // 注册表资源从宇宙里复制一份，--material 只需要加载一次
+        .insert_resource((*universe.registry).clone())
+        .insert_resource(universe)
+        .insert_resource(args.world)
+        .insert_resource(args)
         .add_plugins((
+                         WorldConfigPlugin,
                          ResetPipelinePlugin,
                          PipelineSandPlugin,
+                         SimulationPlugin,
+                         BrushPlugin,
+                         EmitterPlugin,
+                         CustomMaterialPlugin,
+                         WorldFilePlugin,
+                         StampPlugin,
+                         ReplayPlugin,
                      ))
 
         .add_systems(Startup, setup);
@@ -0,0 +1,270 @@
+use std::num::NonZeroUsize;
+use std::thread;
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::SplitMix64;
+use crate::blast::Blast;
+use crate::chunks::CHUNK_SIZE;
+use crate::universe::{SandApi, Universe, Wind};
+
+// 棋盘式分块并行 tick：
+// SandApi 最远只访问 ±2 格，所以把块按 (cx % 2, cy % 2) 分成 4 组，同一组的块之间至少隔着一整块，
+// 可以同时更新。每个块连同周围 HALO 格装进一个复用的小 Universe（Universe.regions），
+// 在工作线程上按串行 tick 的扫描顺序更新，然后写回；4 组依次执行，风和细胞更新两个阶段各做一遍。
+//
+// 随机数按块的位置派生：每个阶段从宇宙 rng 抽取一次种子，每个块开始前用种子和块坐标重置区域的 rng，
+// 块内按固定的扫描顺序取数。所以块拿到的随机序列只取决于种子、阶段和块的位置，与块由哪个线程、按什么顺序执行无关。
+// SandApi 写过的格子记在区域的 touched 里，写回时只处理这些格子，不再整个区域拷回去。
+//
+// 确定性模式（--deterministic）：TickSchedule::Serial 也走这里，在当前线程上逐块执行（threads = 1），
+// 与 Checkerboard 任意线程数的结果逐位相同，录像可以换一种调度回放。不开确定性模式时，
+// Serial 是原来的整图扫描（整个宇宙共用一个 rng），随机序列不同，结果不能与分块调度混用。
+// 块的区域不会绕到对边，所以有 Wrap 边界时两种调度都退回整图扫描。
+// 无头模式的 --bench 会比较几种调度的速度，并检查确定性模式下 Serial 与 Checkerboard 的校验和是否一致。
+
+// SandApi 的最大访问距离
+const HALO: i32 = 2;
+
+// 清空后的 burns
+const NO_BURN: Wind = Wind {
+    dx: 0,
+    dy: 0,
+    pressure: 0,
+    density: 0,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TickSchedule {
+    // 原来的单线程全图扫描
+    Serial,
+    // 棋盘式分块，threads 个工作线程
+    Checkerboard { threads: usize },
+}
+
+impl Default for TickSchedule {
+    fn default() -> Self {
+        TickSchedule::Serial
+    }
+}
+
+// 机器的逻辑核数
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Pass {
+    Wind,
+    Update,
+}
+
+// 一个块的更新任务：region 是块加上 HALO 后裁剪到宇宙范围内的区域，在工作线程上装入
+struct ChunkJob {
+    cx: i32,
+    cy: i32,
+    x0: i32,
+    y0: i32,
+    width: i32,
+    height: i32,
+    // 块本身（不含 HALO）在 region 中的范围
+    inner_x: (i32, i32),
+    inner_y: (i32, i32),
+    region: Universe,
+}
+
+impl ChunkJob {
+    fn run(&mut self, source: &Universe, pass: Pass, pass_seed: u64) {
+        source.fill_region(&mut self.region, self.x0, self.y0, self.width, self.height);
+        let (ix0, ix1) = self.inner_x;
+        let (iy0, iy1) = self.inner_y;
+        let universe = &mut self.region;
+        universe.rng = SplitMix64::seed_from_u64(chunk_seed(pass_seed, self.cx, self.cy));
+        for i in 0..(ix1 - ix0) {
+            // 与串行 tick 相同：细胞更新阶段按 generation 的奇偶交替扫描方向
+            let x = if pass == Pass::Update && universe.generation % 2 == 0 {
+                ix1 - 1 - i
+            } else {
+                ix0 + i
+            };
+            for y in iy0..iy1 {
+                let cell = universe.get_cell(x, y);
+                match pass {
+                    Pass::Wind => {
+                        let wind = universe.get_wind(x, y);
+                        Universe::blow_wind(cell, wind, SandApi { universe: &mut *universe, x, y });
+                    }
+                    Pass::Update => {
+                        // 会随机变化的物种让所在的块保持清醒，写回时唤醒
+                        if universe.registry.always_active(cell.species) {
+                            let idx = universe.get_index(x, y);
+                            universe.touch(idx);
+                        }
+                        Universe::update_cell(cell, SandApi { universe: &mut *universe, x, y });
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Universe {
+    pub(crate) fn tick_checkerboard(&mut self, threads: usize) {
+        self.chunks.begin_tick(&self.boundaries);
+        self.run_pass(Pass::Wind, threads);
+        self.generation = self.generation.wrapping_add(1);
+        self.clear_active_burns();
+        self.run_pass(Pass::Update, threads);
+        self.chunks.end_tick();
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    // 串行 tick 在更新每个细胞之前清空它的 burns，这里在细胞更新阶段开始前一次清空所有要处理的块，
+    // 区域装入的就是清空后的值，写回时不用再拷回没被 set_fluid 写过的格子
+    fn clear_active_burns(&mut self) {
+        for cx in 0..self.chunks.cols() {
+            for cy in 0..self.chunks.rows() {
+                if !self.chunks.is_active(cx, cy) {
+                    continue;
+                }
+                let rows = self.chunk_rows(cy);
+                for x in cx * CHUNK_SIZE..((cx + 1) * CHUNK_SIZE).min(self.width) {
+                    let start = self.get_index(x, rows.start);
+                    let end = self.get_index(x, rows.end);
+                    self.burns[start..end].fill(NO_BURN);
+                }
+            }
+        }
+    }
+
+    fn run_pass(&mut self, pass: Pass, threads: usize) {
+        let pass_seed: u64 = self.rng.gen();
+        let mut regions = std::mem::take(&mut self.regions);
+        for phase in 0..4 {
+            let mut jobs = Vec::new();
+            for cx in (phase % 2..self.chunks.cols()).step_by(2) {
+                for cy in (phase / 2..self.chunks.rows()).step_by(2) {
+                    if self.chunks.is_active(cx, cy) {
+                        let region = regions.pop().unwrap_or_else(|| Universe::with_seed(0, 0, self.seed));
+                        jobs.push(self.chunk_job(cx, cy, region));
+                    }
+                }
+            }
+            run_jobs(self, &mut jobs, pass, pass_seed, threads);
+            for job in jobs {
+                self.write_back(&job, pass);
+                regions.push(job.region);
+            }
+        }
+        self.regions = regions;
+    }
+
+    fn chunk_job(&self, cx: i32, cy: i32, region: Universe) -> ChunkJob {
+        let chunk_x = (cx * CHUNK_SIZE, ((cx + 1) * CHUNK_SIZE).min(self.width));
+        let chunk_y = (cy * CHUNK_SIZE, ((cy + 1) * CHUNK_SIZE).min(self.height));
+        let x0 = (chunk_x.0 - HALO).max(0);
+        let y0 = (chunk_y.0 - HALO).max(0);
+        let x1 = (chunk_x.1 + HALO).min(self.width);
+        let y1 = (chunk_y.1 + HALO).min(self.height);
+        ChunkJob {
+            cx,
+            cy,
+            x0,
+            y0,
+            width: x1 - x0,
+            height: y1 - y0,
+            inner_x: (chunk_x.0 - x0, chunk_x.1 - x0),
+            inner_y: (chunk_y.0 - y0, chunk_y.1 - y0),
+            region,
+        }
+    }
+
+    // 把块里被写过的格子写回宇宙，并唤醒发生变化的块
+    fn write_back(&mut self, job: &ChunkJob, pass: Pass) {
+        let region = &job.region;
+        let (ix0, ix1) = job.inner_x;
+        let (iy0, iy1) = job.inner_y;
+        for &src in region.touched.iter().flatten() {
+            let x = src as i32 / region.height;
+            let y = src as i32 % region.height;
+            let gx = job.x0 + x;
+            let gy = job.y0 + y;
+            let dst = self.get_index(gx, gy);
+            let old = self.cells[dst];
+            let new = region.cells[src];
+            let changed = old.species != new.species || old.ra != new.ra || old.rb != new.rb;
+            let inner = x >= ix0 && x < ix1 && y >= iy0 && y < iy1;
+            if changed || (pass == Pass::Update && inner && self.registry.always_active(old.species)) {
+                self.chunks.wake(gx, gy);
+            }
+            self.cells[dst] = new;
+            self.burns[dst] = region.burns[src];
+            self.temps[dst] = region.temps[src];
+        }
+        // 块里请求的爆炸换算成宇宙坐标，按写回顺序排队
+        for blast in region.blasts.iter() {
+            self.blasts.push(Blast {
+                x: job.x0 + blast.x,
+                y: job.y0 + blast.y,
+                ..*blast
+            });
+        }
+        if let Some((dx0, dx1)) = region.dirty {
+            self.mark_dirty(job.x0 + dx0);
+            self.mark_dirty(job.x0 + dx1);
+        }
+    }
+}
+
+// 块 (cx, cy) 在这个阶段的 rng 种子
+fn chunk_seed(pass_seed: u64, cx: i32, cy: i32) -> u64 {
+    let position = (cx as u32 as u64) << 32 | cy as u32 as u64;
+    pass_seed ^ position.wrapping_mul(0x9e3779b97f4a7c15)
+}
+
+// 同一组的块互不重叠，各自从 source 装入区域后独立更新，可以分给多个线程
+fn run_jobs(source: &Universe, jobs: &mut [ChunkJob], pass: Pass, pass_seed: u64, threads: usize) {
+    if threads <= 1 || jobs.len() <= 1 {
+        for job in jobs.iter_mut() {
+            job.run(source, pass, pass_seed);
+        }
+        return;
+    }
+    let per_thread = (jobs.len() + threads - 1) / threads;
+    thread::scope(|scope| {
+        for batch in jobs.chunks_mut(per_thread) {
+            scope.spawn(move || {
+                for job in batch.iter_mut() {
+                    job.run(source, pass, pass_seed);
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::species::Species;
+
+    fn checksum_after(schedule: TickSchedule, deterministic: bool) -> u64 {
+        let mut universe = Universe::with_seed(200, 160, 7);
+        universe.paint(40, 40, 30, Species::Sand);
+        universe.paint(120, 40, 30, Species::Water);
+        universe.paint(100, 120, 40, Species::Plant);
+        universe.paint(100, 120, 10, Species::Fire);
+        universe.paint(170, 100, 12, Species::Gunpowder);
+        universe.schedule = schedule;
+        universe.deterministic = deterministic;
+        for _ in 0..40 {
+            universe.tick();
+        }
+        universe.checksum()
+    }
+
+    #[test]
+    fn checkerboard_is_independent_of_thread_count() {
+        let one = checksum_after(TickSchedule::Checkerboard { threads: 1 }, true);
+        assert_eq!(checksum_after(TickSchedule::Checkerboard { threads: 2 }, true), one);
+        assert_eq!(checksum_after(TickSchedule::Checkerboard { threads: 8 }, true), one);
+        assert_eq!(checksum_after(TickSchedule::Serial, true), one);
+    }
+}
@@ -1,5 +1,3 @@
-
-use std::num::NonZeroU64;
 use std::time::Duration;
 use bevy::core::{Pod, Zeroable};
 use bevy::prelude::*;
@@ -11,11 +9,14 @@ use bevy::render::render_resource::{BindGroup, BindGroupDescriptor, BindGroupEnt
 use bevy::render::render_resource::binding_types::{sampler, texture_2d, uniform_buffer};
 use bevy::render::renderer::{RenderContext, RenderDevice, RenderQueue};
 use bevy::render::texture::BevyDefault;
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::SplitMix64;
 use crate::{ GameOfLifeState};
 use crate::pipeline_sand::{PipelineSand, SandUniform};
+use crate::replay::ReplayPlayer;
 use crate::universe::*;
 use crate::species::Species;
+use crate::world_config::WorldConfig;
 
 pub struct ResetPipelinePlugin;
 
@@ -61,8 +62,9 @@ impl FromWorld for InitTexture {
     fn from_world(world: &mut World) -> Self {
 
         let render_device = world.resource::<RenderDevice>();
-        let width=300;
-        let height =300;
+        let config = world.resource::<WorldConfig>();
+        let width = config.width;
+        let height = config.height;
         let texture_descriptor = TextureDescriptor {
             label: Some("Texture"),
             size: Extent3d {
@@ -133,8 +135,9 @@ impl ResetPipeline{
             return;
         };
         println!("ResetPipeline444");
-        let width=300;
-        let height=300;
+        let config = world.resource::<WorldConfig>();
+        let width = config.width;
+        let height = config.height;
 
 
         let texture_descriptor = TextureDescriptor {
@@ -445,14 +448,17 @@ impl render_graph::Node for GameOfLifeNode {
 // render_pass.set_camera_viewport(viewport);
 // }
 
+// 开场动画按墙钟时间画，画的时刻不确定；它用自己的 rng（由宇宙种子派生），
+// 不消耗 Universe.rng，所以不影响同一种子下模拟的随机序列
+const BOOT_SEED_SALT: u64 = 0xb007_5eed_a11c_e5e5;
+
 #[derive(Resource)]
 struct BootState {
     step: u32,
     sub_step: u32,
     timer: Timer,
     stop_boot: bool,
-    width: f32,
-    height: f32,
+    rng: Option<SplitMix64>,
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
@@ -467,8 +473,7 @@ impl Plugin for ResetPipelinePlugin {
                 sub_step: 0,
                 timer: Timer::from_seconds(0.016, TimerMode::Once),
                 stop_boot: false,
-                width: 300.0,
-                height: 300.0,
+                rng: None,
             })
             .add_plugins((
                 ExtractComponentPlugin::<ClearUniform>::default(),
@@ -506,38 +511,49 @@ impl Plugin for ResetPipelinePlugin {
 
 fn setup(
     mut commands: Commands,
+    config: Res<WorldConfig>,
 ){
     commands.spawn(
-       SandUniform::default()
+       SandUniform::with_resolution(config.width as f32, config.height as f32)
     );
     println!("3_SandUniform_init");
 }
 fn boot_system(
     time: Res<Time>,
     mut boot_state: ResMut<BootState>,
+    config: Res<WorldConfig>,
     mut universe:ResMut<Universe>,
-    mut render_queue: ResMut<RenderQueue>,
-    mut render_device: ResMut<RenderDevice>,
-    mut sand_uniform:Query<Entity,With<SandUniform>>
+    mut sand_uniform:Query<Entity,With<SandUniform>>,
+    player: Option<Res<ReplayPlayer>>,
 )
 {
 
     if boot_state.stop_boot {
         return;
     }
+    // 回放从录像保存的世界开始，开场动画会让回放失步，回放结束后也不再播放
+    if player.is_some() {
+        boot_state.stop_boot = true;
+        return;
+    }
 
     boot_state.timer.tick(time.delta());
     if !boot_state.timer.finished() {
         return;
     }
+    let seed = universe.seed() ^ BOOT_SEED_SALT;
+    let mut rng = boot_state.rng.take().unwrap_or_else(|| SplitMix64::seed_from_u64(seed));
+    // 开场动画按 WorldConfig 的尺寸铺满宇宙
+    let width = config.width as f32;
+    let height = config.height as f32;
     match boot_state.step {
 
         0 => {
             // 第一个循环，绘制沙子（Species.Sand）
             let x = 5.0 + boot_state.sub_step as f32 * 10.0;
-            if x <= boot_state.width - 5.0 {
-                let y = (boot_state.height - 40.0 + 5.0 * (x / 20.0).sin()) as i32;
-                let size = (rand::thread_rng().gen_range(0.0..6.0) + 10.0) as i32;
+            if x <= width - 5.0 {
+                let y = (height - 40.0 + 5.0 * (x / 20.0).sin()) as i32;
+                let size = (rng.gen_range(0.0..6.0) + 10.0) as i32;
                 universe.paint( x as i32, -y, size, Species::Sand);
                 boot_state.sub_step += 1;
                 boot_state.timer.set_duration(Duration::from_secs_f32(0.016));
@@ -550,9 +566,9 @@ fn boot_system(
         }
         1 => {
             // 第二个循环，绘制种子（Species.Seed）
-            let x = 40.0 + boot_state.sub_step as f32 * (50.0 + rand::thread_rng().gen_range(0.0..10.0));
-            if x <= boot_state.width - 40.0 {
-                let y = (boot_state.height / 2.0 + 20.0 * (x / 20.0).sin()) as i32;
+            let x = 40.0 + boot_state.sub_step as f32 * (50.0 + rng.gen_range(0.0..10.0));
+            if x <= width - 40.0 {
+                let y = (height / 2.0 + 20.0 * (x / 20.0).sin()) as i32;
                 let size = 6;
                 universe.paint( x as i32, -y, size, Species::Seed);
                 boot_state.sub_step += 1;
@@ -560,26 +576,14 @@ fn boot_system(
                 boot_state.timer.reset();
             } else {
                 // 可以在这里添加后续步骤
-                let data:Vec<CellData>= universe.cells.iter().map(|cell| {
-                    CellData {
-                        species: cell.species.into(),
-                        ra: cell.ra,
-                        rb: cell.rb,
-                        clock: cell.clock,
-                    }
-                }).collect();
-               let cell_buffer= render_device.create_buffer_with_data(&BufferInitDescriptor {
-                    label: Some("cell"),
-                    contents: bytemuck::cast_slice(data.as_slice()),
-                    usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
-                });
-                render_queue.write_buffer_with(&*cell_buffer, 0 as u64 ,NonZeroU64::new(cell_buffer.size()).unwrap());
+                // 细胞数据由 pipeline_sand 的 CellUpload 每帧上传，这里不再单独创建缓冲区
                 println!("&&&&&&&&&&&&{:?}",universe.cells.len());
                 boot_state.stop_boot = true;
             }
         }
         _ => {}
     }
+    boot_state.rng = Some(rng);
     //
     // sand_uniform.uniforms().write_buffer()
     // s.write_buffer()
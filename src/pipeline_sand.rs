@@ -2,13 +2,17 @@ use std::borrow::Cow;
 use bevy::core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state;
 use bevy::prelude::*;
 use bevy::render::extract_component::{ComponentUniforms, ExtractComponent, ExtractComponentPlugin, UniformComponentPlugin};
-use bevy::render::render_resource::{BindGroup, BindGroupDescriptor, BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, Buffer, BufferInitDescriptor, BufferUsages, CachedRenderPipelineId, ColorTargetState, ColorWrites, Extent3d, FragmentState, ImageDataLayout, LoadOp, MultisampleState, Operations, PipelineCache, PrimitiveState, RenderPassColorAttachment, RenderPassDescriptor, RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor, ShaderStages, ShaderType, StoreOp, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, VertexBufferLayout, VertexFormat, VertexState, VertexStepMode};
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use bevy::render::render_resource::{BindGroup, BindGroupDescriptor, BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, Buffer, BufferInitDescriptor, BufferUsages, CachedRenderPipelineId, ColorTargetState, ColorWrites, Extent3d, FragmentState, ImageCopyTexture, ImageDataLayout, LoadOp, Origin3d, MultisampleState, Operations, PipelineCache, PrimitiveState, RenderPassColorAttachment, RenderPassDescriptor, RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor, ShaderStages, ShaderType, StoreOp, Texture, TextureDescriptor, TextureAspect, TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, VertexBufferLayout, VertexFormat, VertexState, VertexStepMode};
 use bevy::render::render_resource::binding_types::{sampler, texture_2d, uniform_buffer};
-use bevy::render::RenderApp;
+use bevy::render::{Render, RenderApp, RenderSet};
 use bevy::render::renderer::{RenderContext, RenderDevice, RenderQueue};
 use bevy::render::texture::{BevyDefault, TextureFormatPixelInfo};
 use bytemuck::{Pod, Zeroable};
-use crate::universe::Universe;
+use crate::registry::SpeciesRegistry;
+use crate::species::Species;
+use crate::universe::{CellData, Universe};
+use crate::world_config::{WorldConfig, DEFAULT_WORLD_HEIGHT, DEFAULT_WORLD_WIDTH};
 
 pub struct  PipelineSandPlugin;
 
@@ -23,10 +27,13 @@ impl Plugin for  PipelineSandPlugin {
                 //
                 // ExtractComponentPlugin::<SanVertexInput>::default(),
                 // UniformComponentPlugin::<SanVertexInput>::default(),
+                ExtractResourcePlugin::<CellUpload>::default(),
                 ))
-
+            .init_resource::<CellUpload>()
+            .add_systems(PostUpdate, (queue_cell_upload, sync_sand_resolution, sync_custom_color))
         ;
-
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.add_systems(Render, prepare_cell_texture.in_set(RenderSet::Prepare));
     }
 
     fn finish(&self, app: &mut App) {
@@ -47,18 +54,135 @@ pub struct SandUniform {
     dpi: f32,
     resolution: Vec2,
     // 0 1
-    is_snapshot: u32
+    is_snapshot: u32,
+    // 自定义材质 X 的颜色，RGBA8 打包（shader 里用 unpack4x8unorm 解开）
+    custom_color: u32,
 }
 
 impl Default for  SandUniform {
     fn default() -> Self {
+        Self::with_resolution(DEFAULT_WORLD_WIDTH as f32, DEFAULT_WORLD_HEIGHT as f32)
+    }
+}
+
+impl SandUniform {
+    pub fn with_resolution(width: f32, height: f32) -> Self {
         Self{
             t:0.,
             dpi:0.,
-            resolution:Vec2::new(300.,300.),
-            is_snapshot:0
+            resolution:Vec2::new(width, height),
+            is_snapshot:0,
+            custom_color:0,
+        }
+    }
+}
+
+// 宇宙尺寸变化时更新 shader 的分辨率
+fn sync_sand_resolution(
+    config: Res<WorldConfig>,
+    mut uniforms: Query<&mut SandUniform>,
+) {
+    if !config.is_changed() {
+        return;
+    }
+    let resolution = Vec2::new(config.width as f32, config.height as f32);
+    for mut uniform in uniforms.iter_mut() {
+        if uniform.resolution != resolution {
+            uniform.resolution = resolution;
+        }
+    }
+}
+
+// 注册表里物种 X 的颜色变化时（加载了新的材质配置）更新 shader
+fn sync_custom_color(
+    registry: Res<SpeciesRegistry>,
+    mut uniforms: Query<&mut SandUniform>,
+) {
+    let color = registry.get(Species::X).map_or(0, |def| u32::from_le_bytes(def.color));
+    for mut uniform in uniforms.iter_mut() {
+        if uniform.custom_color != color {
+            uniform.custom_color = color;
+        }
+    }
+}
+
+// 每帧从 Universe 取出脏列，拷贝成 CellData 交给渲染世界上传到 sand.wgsl 采样的数据纹理。
+// Universe 的下标是 x * height + y，所以纹理的一行对应 Universe 的一列（shader 里用 .yx 交换回来）。
+#[derive(Resource, Clone, Default, ExtractResource)]
+pub struct CellUpload {
+    // 需要上传的列范围（闭区间），None 表示本帧没有变化
+    pub rows: Option<(u32, u32)>,
+    pub height: u32,
+    pub data: Vec<CellData>,
+}
+
+fn queue_cell_upload(
+    mut universe: ResMut<Universe>,
+    mut upload: ResMut<CellUpload>,
+) {
+    let Some((x0, x1)) = universe.take_dirty() else {
+        // 只在状态变化时写入，避免每帧都触发提取
+        if upload.rows.is_some() {
+            upload.rows = None;
         }
+        return;
+    };
+    let height = universe.height as usize;
+    let start = x0 as usize * height;
+    let end = (x1 as usize + 1) * height;
+
+    let upload = upload.as_mut();
+    upload.rows = Some((x0 as u32, x1 as u32));
+    upload.height = height as u32;
+    upload.data.clear();
+    upload.data.extend(universe.cells[start..end].iter().map(|cell| cell.to_cell_data()));
+}
+
+fn prepare_cell_texture(
+    upload: Res<CellUpload>,
+    config: Res<WorldConfig>,
+    pipeline: Option<ResMut<PipelineSand>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    let Some(mut pipeline) = pipeline else {
+        return;
+    };
+    // 宇宙尺寸变化后重建纹理，随后的整帧上传会填满它
+    let size = pipeline.image_texture.size();
+    if (size.width, size.height) != (config.height, config.width) {
+        let (image_texture, image_texture_view, texture_view) =
+            create_sand_textures(&render_device, &render_queue, config.width, config.height);
+        pipeline.image_texture = image_texture;
+        pipeline.image_texture_view = image_texture_view;
+        pipeline.texture_view = texture_view;
     }
+    let Some((x0, x1)) = upload.rows else {
+        return;
+    };
+    let size = pipeline.image_texture.size();
+    if upload.height != size.width || x1 >= size.height {
+        return;
+    }
+    render_queue.write_texture(
+        ImageCopyTexture {
+            texture: &pipeline.image_texture,
+            mip_level: 0,
+            origin: Origin3d { x: 0, y: x0, z: 0 },
+            aspect: TextureAspect::All,
+        },
+        bytemuck::cast_slice(upload.data.as_slice()),
+        ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(upload.height * std::mem::size_of::<CellData>() as u32),
+            rows_per_image: None,
+        },
+        Extent3d {
+            width: upload.height,
+            height: x1 - x0 + 1,
+            depth_or_array_layers: 1,
+        },
+    );
 }
 
 // #[repr(C)]
@@ -73,6 +197,8 @@ pub struct  PipelineSand{
     pub sand_bind_layout: BindGroupLayout,
     // pub sand_vertex_bind_layout: BindGroupLayout,
     pub texture_view: TextureView,
+    // Universe.cells 上传的目标纹理
+    pub image_texture: Texture,
     pub image_texture_view: TextureView,
     pub sampler: Sampler,
 }
@@ -147,6 +273,56 @@ impl PipelineSand {
         pass.draw(0..3, 0..1);
     }
 }
+// 按宇宙尺寸创建数据纹理和渲染目标。
+// 数据纹理的一行对应 Universe 的一列，所以它的宽是宇宙的高、高是宇宙的宽。
+fn create_sand_textures(
+    render_device: &RenderDevice,
+    render_queue: &RenderQueue,
+    width: u32,
+    height: u32,
+) -> (Texture, TextureView, TextureView) {
+    let mut image = Image::default();
+    // // image.data = vec![255 as u8; cell_count];
+    image.data = vec![255u8; (width * height * 4) as usize];
+    image.texture_descriptor.size.width = height;
+    image.texture_descriptor.size.height = width;
+    // 数据纹理必须是线性格式，否则 sRGB 转换会破坏 shader 里从红色通道解码的物种
+    image.texture_descriptor.format = TextureFormat::Rgba8Unorm;
+    let image_texture = render_device.create_texture(&image.texture_descriptor);
+    let format_size = image.texture_descriptor.format.pixel_size();
+    render_queue.write_texture(
+        image_texture.as_image_copy(),
+        &image.data,
+        ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(image.width() * format_size as u32),
+            rows_per_image: None,
+        },
+        image.texture_descriptor.size,
+    );
+
+    let texture_descriptor = TextureDescriptor {
+        label: Some("Texture"),
+        size: Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: TextureFormat::Rgba8UnormSrgb,
+        usage: TextureUsages::TEXTURE_BINDING |TextureUsages::COPY_DST |TextureUsages::RENDER_ATTACHMENT ,
+            //| TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[]
+    };
+    let data_texture = render_device.create_texture(&texture_descriptor);
+
+    let image_texture_view = image_texture.create_view(&TextureViewDescriptor::default());
+    let texture_view = data_texture.create_view(&TextureViewDescriptor::default());
+    (image_texture, image_texture_view, texture_view)
+}
+
 impl FromWorld for PipelineSand {
     fn from_world(world: &mut World) -> Self {
 
@@ -155,52 +331,10 @@ impl FromWorld for PipelineSand {
         let render_queue =world.resource::<RenderQueue>();
 
 
-        let width=300.0 as u32;
-        let height=300. as u32;
+        let config = world.resource::<WorldConfig>();
         let sampler1=render_device.create_sampler(&SamplerDescriptor::default());
-
-
-        let mut image = Image::default();
-        // // image.data = vec![255 as u8; cell_count];
-        let  new_data = vec![255u8; (width * height * 4) as usize];
-        image.data=new_data;
-        image.texture_descriptor.size.width=width;
-        image.texture_descriptor.size.height=height;
-        let format_size = image.texture_descriptor.format.pixel_size();
-        let image_texture = render_device.create_texture(&image.texture_descriptor);
-        let texture_descriptor = TextureDescriptor {
-            label: Some("Texture"),
-            size: Extent3d {
-                width,
-                height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8UnormSrgb,
-            usage: TextureUsages::TEXTURE_BINDING |TextureUsages::COPY_DST |TextureUsages::RENDER_ATTACHMENT ,
-                //| TextureUsages::RENDER_ATTACHMENT,
-            view_formats: &[]
-        };
-        let data_texture = render_device.create_texture(&texture_descriptor);
-
-        let format_size = texture_descriptor.format.pixel_size();
-        render_queue.write_texture(
-            image_texture.as_image_copy(),
-            &image.data,
-            ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(image.width() * format_size as u32),
-                rows_per_image: None,
-            },
-            texture_descriptor.size,
-
-        );
-
-
-        let image_texture_view = image_texture.create_view(&TextureViewDescriptor::default());
-        let texture_view = data_texture.create_view(&TextureViewDescriptor::default());
+        let (image_texture, image_texture_view, texture_view) =
+            create_sand_textures(render_device, render_queue, config.width, config.height);
         // let sampler1 = render_device.create_sampler(&SamplerDescriptor::default());
 
         let frag_shader = asser_server.load("shader/sand.wgsl");
@@ -252,6 +386,7 @@ impl FromWorld for PipelineSand {
             sand_bind_layout:sand_layout,
             // sand_vertex_bind_layout:sand_vertex_layout,
             texture_view,
+            image_texture,
             image_texture_view,
             sampler:sampler1
 
@@ -0,0 +1,498 @@
+use bevy::prelude::Resource;
+use crate::custom_material::CustomMaterial;
+use crate::species::*;
+use crate::temperature::ICE_TEMP;
+use crate::universe::{Cell, SandApi};
+
+// 物种的更新函数，签名与 species.rs 中的 update_* 一致
+pub type UpdateFn = fn(Cell, SandApi);
+
+// 未登记物种（例如 sandspiel 里被 hack 出来的 "BELP"）使用的风阈值。
+// 原版里阈值是写死的，默认值保留了这个行为，见 https://sandspiel.club/#eMlYGC52XIto0NM1WjaJ
+pub const DEFAULT_WIND_THRESHOLD: i32 = 40;
+
+// 一个物种的全部数据：
+// update：每个 tick 调用的规则，None 表示静止（Empty、Wall）
+// wind_threshold：风速超过这个值才会吹动该物种
+// wind_jump：被风往上吹时，上方两格都是空的就直接跳两格（见 Universe::blow_wind）
+// density：相对密度 0..255，越大越重
+// color：代表色，用于导出预览和颜色图例
+// import_buckets：rgba_to_species 中 (色相, 亮度) 分桶到该物种的映射
+// always_active：即使一段时间没有变化也会随机发生变化，所在的块不能休眠（见 chunks.rs）
+// toughness：抗爆程度 0..255，爆炸强度超过它时被炸毁，超过一半时被推开，255 表示炸不坏也推不动（见 blast.rs）
+// fixed_temp：该物种所在格子的温度固定为这个值，是温度场的热源或冷源（见 temperature.rs）
+#[derive(Clone)]
+pub struct SpeciesDef {
+    pub species: Species,
+    pub name: String,
+    pub update: Option<UpdateFn>,
+    pub wind_threshold: i32,
+    pub wind_jump: bool,
+    pub density: u8,
+    pub toughness: u8,
+    pub color: [u8; 4],
+    pub import_buckets: Vec<(usize, usize)>,
+    pub always_active: bool,
+    pub fixed_temp: Option<i16>,
+}
+
+// 物种注册表，按物种 id 索引。新增材质只需要在 Default 里登记一次。
+// custom 是自定义材质 X 的参数（见 custom_material.rs），update_x 从这里读取。
+#[derive(Resource, Clone)]
+pub struct SpeciesRegistry {
+    defs: Vec<Option<SpeciesDef>>,
+    custom: CustomMaterial,
+}
+
+impl SpeciesRegistry {
+    pub fn empty() -> Self {
+        Self {
+            defs: vec![None; 256],
+            custom: CustomMaterial::default(),
+        }
+    }
+
+    // 登记或覆盖一个物种
+    pub fn register(&mut self, def: SpeciesDef) {
+        let id = def.species as u8 as usize;
+        self.defs[id] = Some(def);
+    }
+
+    pub fn get(&self, species: Species) -> Option<&SpeciesDef> {
+        self.defs[species as u8 as usize].as_ref()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SpeciesDef> {
+        self.defs.iter().flatten()
+    }
+
+    pub fn update_fn(&self, species: Species) -> Option<UpdateFn> {
+        self.get(species).and_then(|def| def.update)
+    }
+
+    pub fn wind_threshold(&self, species: Species) -> i32 {
+        self.get(species).map_or(DEFAULT_WIND_THRESHOLD, |def| def.wind_threshold)
+    }
+
+    pub fn wind_jump(&self, species: Species) -> bool {
+        self.get(species).map_or(false, |def| def.wind_jump)
+    }
+
+    pub fn density(&self, species: Species) -> u8 {
+        self.get(species).map_or(0, |def| def.density)
+    }
+
+    pub fn toughness(&self, species: Species) -> u8 {
+        self.get(species).map_or(0, |def| def.toughness)
+    }
+
+    pub fn always_active(&self, species: Species) -> bool {
+        self.get(species).map_or(false, |def| def.always_active)
+    }
+
+    pub fn fixed_temp(&self, species: Species) -> Option<i16> {
+        self.get(species).and_then(|def| def.fixed_temp)
+    }
+
+    pub fn name(&self, species: Species) -> &str {
+        self.get(species).map_or("Unknown", |def| def.name.as_str())
+    }
+
+    pub fn custom_material(&self) -> &CustomMaterial {
+        &self.custom
+    }
+
+    // 替换自定义材质，同时按它的名字、颜色、风阈值、密度和抗爆程度重新登记物种 X
+    pub fn set_custom_material(&mut self, material: CustomMaterial) {
+        self.register(SpeciesDef {
+            species: Species::X,
+            name: material.name.clone(),
+            update: Some(update_x),
+            wind_threshold: material.wind_threshold,
+            wind_jump: false,
+            density: material.density,
+            toughness: material.toughness,
+            color: material.color,
+            import_buckets: vec![],
+            always_active: false,
+            fixed_temp: None,
+        });
+        self.custom = material;
+    }
+
+    // 按名字查找物种，不区分大小写
+    pub fn species_named(&self, name: &str) -> Option<Species> {
+        self.iter()
+            .find(|def| def.name.eq_ignore_ascii_case(name))
+            .map(|def| def.species)
+    }
+
+    // rgba_to_species 的非灰度分桶查找
+    pub fn species_for_bucket(&self, hue_index: usize, lightness_index: usize) -> Option<Species> {
+        self.iter()
+            .find(|def| def.import_buckets.contains(&(hue_index, lightness_index)))
+            .map(|def| def.species)
+    }
+}
+
+impl Default for SpeciesRegistry {
+    fn default() -> Self {
+        let mut registry = SpeciesRegistry::empty();
+        // 分桶的色相下标：0 红 1 黄 2 绿 3 绿2 4 蓝 5 紫 6 紫罗兰
+        registry.register(SpeciesDef {
+            species: Species::Empty,
+            name: "Empty".into(),
+            update: None,
+            wind_threshold: 500,
+            wind_jump: false,
+            density: 0,
+            toughness: 0,
+            color: [255, 255, 255, 255],
+            import_buckets: vec![],
+            always_active: false,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Wall,
+            name: "Wall".into(),
+            update: None,
+            wind_threshold: 500,
+            wind_jump: false,
+            density: 255,
+            toughness: 255,
+            color: [100, 98, 92, 255],
+            import_buckets: vec![],
+            always_active: false,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Sand,
+            name: "Sand".into(),
+            update: Some(update_sand),
+            wind_threshold: 30,
+            wind_jump: true,
+            density: 150,
+            toughness: 40,
+            color: [220, 190, 110, 255],
+            import_buckets: vec![],
+            always_active: false,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Water,
+            name: "Water".into(),
+            update: Some(update_water),
+            wind_threshold: 40,
+            wind_jump: true,
+            density: 100,
+            toughness: 10,
+            color: [70, 140, 230, 255],
+            import_buckets: vec![(4, 0)],
+            always_active: false,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Stone,
+            name: "Stone".into(),
+            update: Some(update_stone),
+            wind_threshold: 70,
+            wind_jump: false,
+            density: 200,
+            toughness: 160,
+            color: [130, 130, 125, 255],
+            import_buckets: vec![(4, 2)],
+            always_active: false,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Ice,
+            name: "Ice".into(),
+            update: Some(update_ice),
+            wind_threshold: 60,
+            wind_jump: false,
+            density: 95,
+            toughness: 100,
+            color: [170, 210, 240, 255],
+            import_buckets: vec![(4, 1)],
+            always_active: true,
+            fixed_temp: Some(ICE_TEMP),
+        });
+        registry.register(SpeciesDef {
+            species: Species::Gas,
+            name: "Gas".into(),
+            update: Some(update_gas),
+            wind_threshold: 5,
+            wind_jump: false,
+            density: 5,
+            toughness: 0,
+            color: [200, 160, 200, 255],
+            import_buckets: vec![(1, 2)],
+            always_active: true,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Cloner,
+            name: "Cloner".into(),
+            update: Some(update_cloner),
+            wind_threshold: 500,
+            wind_jump: false,
+            density: 255,
+            toughness: 255,
+            color: [200, 70, 170, 255],
+            import_buckets: vec![(6, 0)],
+            always_active: true,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Sink,
+            name: "Sink".into(),
+            update: Some(update_sink),
+            wind_threshold: 500,
+            wind_jump: false,
+            density: 255,
+            toughness: 255,
+            color: [230, 170, 210, 255],
+            import_buckets: vec![(6, 2)],
+            always_active: false,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Mite,
+            name: "Mite".into(),
+            update: Some(update_mite),
+            wind_threshold: 30,
+            wind_jump: true,
+            density: 120,
+            toughness: 10,
+            color: [220, 60, 230, 255],
+            import_buckets: vec![(6, 1)],
+            always_active: true,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Wood,
+            name: "Wood".into(),
+            update: Some(update_wood),
+            wind_threshold: 70,
+            wind_jump: false,
+            density: 140,
+            toughness: 90,
+            color: [120, 80, 40, 255],
+            import_buckets: vec![(1, 0)],
+            always_active: false,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Plant,
+            name: "Plant".into(),
+            update: Some(update_plant),
+            wind_threshold: 60,
+            wind_jump: false,
+            density: 120,
+            toughness: 30,
+            color: [80, 200, 90, 255],
+            import_buckets: vec![(2, 0), (3, 0)],
+            always_active: true,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Fungus,
+            name: "Fungus".into(),
+            update: Some(update_fungus),
+            wind_threshold: 54,
+            wind_jump: false,
+            density: 120,
+            toughness: 30,
+            color: [220, 140, 120, 255],
+            import_buckets: vec![(5, 2)],
+            always_active: true,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Seed,
+            name: "Seed".into(),
+            update: Some(update_seed),
+            wind_threshold: 35,
+            wind_jump: false,
+            density: 110,
+            toughness: 20,
+            color: [240, 200, 80, 255],
+            import_buckets: vec![(5, 1)],
+            always_active: true,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Fire,
+            name: "Fire".into(),
+            update: Some(update_fire),
+            wind_threshold: 5,
+            wind_jump: false,
+            density: 10,
+            toughness: 0,
+            color: [255, 120, 40, 255],
+            import_buckets: vec![(0, 0)],
+            always_active: true,
+            fixed_temp: Some(600),
+        });
+        registry.register(SpeciesDef {
+            species: Species::Lava,
+            name: "Lava".into(),
+            update: Some(update_lava),
+            wind_threshold: 60,
+            wind_jump: true,
+            density: 170,
+            toughness: 60,
+            color: [240, 80, 30, 255],
+            import_buckets: vec![(0, 1)],
+            always_active: true,
+            fixed_temp: Some(1000),
+        });
+        registry.register(SpeciesDef {
+            species: Species::Acid,
+            name: "Acid".into(),
+            update: Some(update_acid),
+            wind_threshold: 40,
+            wind_jump: true,
+            density: 105,
+            toughness: 10,
+            color: [200, 240, 60, 255],
+            import_buckets: vec![(2, 2), (3, 2)],
+            always_active: true,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Dust,
+            name: "Dust".into(),
+            update: Some(update_dust),
+            wind_threshold: 10,
+            wind_jump: true,
+            density: 60,
+            toughness: 5,
+            color: [230, 150, 220, 255],
+            import_buckets: vec![(2, 1), (3, 1)],
+            always_active: false,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Oil,
+            name: "Oil".into(),
+            update: Some(update_oil),
+            wind_threshold: 50,
+            wind_jump: true,
+            density: 90,
+            toughness: 10,
+            color: [60, 50, 60, 255],
+            import_buckets: vec![(5, 0)],
+            always_active: false,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Rocket,
+            name: "Rocket".into(),
+            update: Some(update_rocket),
+            wind_threshold: 30,
+            wind_jump: true,
+            density: 150,
+            toughness: 60,
+            color: [230, 110, 110, 255],
+            import_buckets: vec![(0, 2)],
+            always_active: true,
+            fixed_temp: None,
+        });
+        // 无法识别的物种 id：静止，使用默认风阈值
+        registry.register(SpeciesDef {
+            species: Species::Unknown,
+            name: "Unknown".into(),
+            update: None,
+            wind_threshold: DEFAULT_WIND_THRESHOLD,
+            wind_jump: false,
+            density: 150,
+            toughness: 100,
+            color: [255, 0, 255, 255],
+            import_buckets: vec![],
+            always_active: false,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Snow,
+            name: "Snow".into(),
+            update: Some(update_snow),
+            wind_threshold: 8,
+            wind_jump: false,
+            density: 40,
+            toughness: 5,
+            color: [225, 235, 250, 255],
+            import_buckets: vec![(4, 3)],
+            always_active: true,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Lightning,
+            name: "Lightning".into(),
+            update: Some(update_lightning),
+            wind_threshold: 500,
+            wind_jump: false,
+            density: 0,
+            toughness: 0,
+            color: [255, 252, 180, 255],
+            // 闪电只存在一瞬间，不从图片导入
+            import_buckets: vec![],
+            always_active: true,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Steam,
+            name: "Steam".into(),
+            update: Some(update_steam),
+            wind_threshold: 5,
+            wind_jump: false,
+            density: 3,
+            toughness: 0,
+            color: [230, 235, 240, 255],
+            import_buckets: vec![(3, 3)],
+            always_active: true,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Gunpowder,
+            name: "Gunpowder".into(),
+            update: Some(update_gunpowder),
+            wind_threshold: 40,
+            wind_jump: false,
+            density: 110,
+            toughness: 20,
+            color: [70, 70, 75, 255],
+            import_buckets: vec![],
+            always_active: false,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Smoke,
+            name: "Smoke".into(),
+            update: Some(update_smoke),
+            wind_threshold: 3,
+            wind_jump: false,
+            density: 2,
+            toughness: 0,
+            color: [120, 120, 125, 255],
+            import_buckets: vec![],
+            always_active: true,
+            fixed_temp: None,
+        });
+        registry.register(SpeciesDef {
+            species: Species::Ash,
+            name: "Ash".into(),
+            update: Some(update_ash),
+            wind_threshold: 15,
+            wind_jump: false,
+            density: 30,
+            toughness: 5,
+            color: [160, 155, 150, 255],
+            import_buckets: vec![],
+            always_active: false,
+            fixed_temp: None,
+        });
+        registry.set_custom_material(CustomMaterial::default());
+        registry
+    }
+}
@@ -0,0 +1,482 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use crate::cli::CliArgs;
+use crate::custom_material::{decode_material, encode_material, CustomMaterial};
+use crate::emitter::{decode_emitters, encode_emitters, Emitter};
+use crate::parallel::TickSchedule;
+use crate::species::Species;
+use crate::stamp::load_svg_on_startup;
+use crate::universe::Universe;
+use crate::world_file::{decode_world, encode_world, replace_universe, ByteReader, WorldFileError};
+
+// 操作录像：记录对 Universe 的每一次修改（画笔、擦除、放置、重置、撤销/重做、加载、发射器、自定义材质）和每一个 tick，
+// 每个 tick 附带 tick 结束时的 Universe::checksum。回放时从录像开头保存的世界和种子重新执行，
+// 每个 tick 之后比较校验和，结果逐位一致；不一致说明回放已经失步。
+//
+// 录像文件格式（小端）：
+//   magic   4 字节 "SSPR"
+//   version u16
+//   seed    u64
+//   deterministic u8，0 = 整图串行扫描，1 = 确定性调度（棋盘式分块或 --deterministic，见 parallel.rs）。
+//     两者的随机序列不同，回放必须使用同一种；确定性的录像可以用任意调度和线程数回放
+//   世界存档长度 u32 + 世界存档（world_file 格式）
+//   事件个数 u32 + 事件：类型 u8，然后是各类型的参数（Tick 的参数是校验和 u64）
+pub const REPLAY_MAGIC: [u8; 4] = *b"SSPR";
+pub const REPLAY_VERSION: u16 = 3;
+
+// 默认的录像路径
+pub const DEFAULT_REPLAY_PATH: &str = "session.replay";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReplayEvent {
+    Tick { checksum: u64 },
+    Paint { x: i32, y: i32, size: i32, species: Species },
+    Erase { x: i32, y: i32, size: i32 },
+    Place { x: i32, y: i32, species: Species },
+    Reset,
+    PushUndo,
+    Undo,
+    Redo,
+    // 录制过程中加载了存档，保存加载后的完整世界
+    Load(Vec<u8>),
+    // 发射器列表被修改，保存修改后的完整列表
+    Emitters(Vec<Emitter>),
+    // 自定义材质 X 的参数，录制开始时和每次重新加载（F5）时写入
+    Material(CustomMaterial),
+}
+
+// 录制中的会话，挂在 Universe 上，由 Universe 的修改方法写入
+#[derive(Clone)]
+pub struct Recorder {
+    seed: u64,
+    deterministic: bool,
+    world: Vec<u8>,
+    events: Vec<ReplayEvent>,
+}
+
+impl Recorder {
+    pub fn push(&mut self, event: ReplayEvent) {
+        self.events.push(event);
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&REPLAY_MAGIC);
+        out.extend_from_slice(&REPLAY_VERSION.to_le_bytes());
+        out.extend_from_slice(&self.seed.to_le_bytes());
+        out.push(self.deterministic as u8);
+        write_bytes(&mut out, &self.world);
+        out.extend_from_slice(&(self.events.len() as u32).to_le_bytes());
+        for event in self.events.iter() {
+            encode_event(&mut out, event);
+        }
+        out
+    }
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+fn write_i32s(out: &mut Vec<u8>, values: &[i32]) {
+    for value in values {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn encode_event(out: &mut Vec<u8>, event: &ReplayEvent) {
+    match event {
+        ReplayEvent::Tick { checksum } => {
+            out.push(0);
+            out.extend_from_slice(&checksum.to_le_bytes());
+        }
+        ReplayEvent::Paint { x, y, size, species } => {
+            out.push(1);
+            write_i32s(out, &[*x, *y, *size]);
+            out.push(*species as u8);
+        }
+        ReplayEvent::Erase { x, y, size } => {
+            out.push(2);
+            write_i32s(out, &[*x, *y, *size]);
+        }
+        ReplayEvent::Place { x, y, species } => {
+            out.push(3);
+            write_i32s(out, &[*x, *y]);
+            out.push(*species as u8);
+        }
+        ReplayEvent::Reset => out.push(4),
+        ReplayEvent::PushUndo => out.push(5),
+        ReplayEvent::Undo => out.push(6),
+        ReplayEvent::Redo => out.push(7),
+        ReplayEvent::Load(world) => {
+            out.push(8);
+            write_bytes(out, world);
+        }
+        ReplayEvent::Emitters(emitters) => {
+            out.push(9);
+            encode_emitters(out, emitters);
+        }
+        ReplayEvent::Material(material) => {
+            out.push(10);
+            encode_material(out, material);
+        }
+    }
+}
+
+fn decode_event(reader: &mut ByteReader) -> Result<ReplayEvent, WorldFileError> {
+    let event = match reader.u8()? {
+        0 => ReplayEvent::Tick { checksum: reader.u64()? },
+        1 => ReplayEvent::Paint {
+            x: reader.i32()?,
+            y: reader.i32()?,
+            size: reader.i32()?,
+            species: Species::from_id(reader.u8()?),
+        },
+        2 => ReplayEvent::Erase {
+            x: reader.i32()?,
+            y: reader.i32()?,
+            size: reader.i32()?,
+        },
+        3 => ReplayEvent::Place {
+            x: reader.i32()?,
+            y: reader.i32()?,
+            species: Species::from_id(reader.u8()?),
+        },
+        4 => ReplayEvent::Reset,
+        5 => ReplayEvent::PushUndo,
+        6 => ReplayEvent::Undo,
+        7 => ReplayEvent::Redo,
+        8 => {
+            let len = reader.u32()? as usize;
+            ReplayEvent::Load(reader.take(len)?.to_vec())
+        }
+        9 => ReplayEvent::Emitters(decode_emitters(reader)?),
+        10 => ReplayEvent::Material(decode_material(reader)?),
+        _ => return Err(WorldFileError::Corrupt("unknown replay event")),
+    };
+    Ok(event)
+}
+
+// 开始录制：用宇宙的种子重置 rng 并清空撤销栈，使录像开头的状态可以完整保存下来。
+// 存档不包含注册表，所以第一条事件是当前的自定义材质
+pub fn start_recording(universe: &mut Universe) {
+    universe.reseed(universe.seed());
+    universe.flush_undos();
+    universe.recorder = Some(Recorder {
+        seed: universe.seed(),
+        deterministic: universe.is_deterministic(),
+        world: encode_world(universe),
+        events: vec![ReplayEvent::Material(universe.registry.custom_material().clone())],
+    });
+}
+
+// 停止录制并返回录像
+pub fn stop_recording(universe: &mut Universe) -> Option<Recorder> {
+    universe.recorder.take()
+}
+
+pub fn save_replay(recorder: &Recorder, path: &Path) -> Result<(), WorldFileError> {
+    fs::write(path, recorder.encode())?;
+    Ok(())
+}
+
+// 一段可以回放的录像
+pub struct Replay {
+    pub seed: u64,
+    pub deterministic: bool,
+    world: Vec<u8>,
+    events: Vec<ReplayEvent>,
+}
+
+pub fn decode_replay(bytes: &[u8]) -> Result<Replay, WorldFileError> {
+    let mut reader = ByteReader::new(bytes);
+    if reader.take(4)? != REPLAY_MAGIC {
+        return Err(WorldFileError::BadMagic);
+    }
+    let version = reader.u16()?;
+    if version != REPLAY_VERSION {
+        return Err(WorldFileError::UnsupportedVersion(version));
+    }
+    let seed = reader.u64()?;
+    let deterministic = match reader.u8()? {
+        0 => false,
+        1 => true,
+        _ => return Err(WorldFileError::Corrupt("unknown tick schedule")),
+    };
+    let world_len = reader.u32()? as usize;
+    let world = reader.take(world_len)?.to_vec();
+    let event_count = reader.u32()? as usize;
+    let mut events = Vec::with_capacity(event_count.min(bytes.len()));
+    for _ in 0..event_count {
+        events.push(decode_event(&mut reader)?);
+    }
+    Ok(Replay { seed, deterministic, world, events })
+}
+
+pub fn load_replay(path: &Path) -> Result<Replay, WorldFileError> {
+    decode_replay(&fs::read(path)?)
+}
+
+// tick 之后的校验和与录像不一致，说明回放已经偏离了录制时的结果
+#[derive(Debug)]
+pub struct ReplayDesync {
+    pub tick: usize,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl fmt::Display for ReplayDesync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "replay desync at tick {}: expected checksum {:#018x}, got {:#018x}", self.tick, self.expected, self.actual)
+    }
+}
+
+// 回放器：按顺序把录像中的事件重新作用到宇宙上
+#[derive(Resource)]
+pub struct ReplayPlayer {
+    replay: Replay,
+    cursor: usize,
+    ticks: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        Self { replay, cursor: 0, ticks: 0 }
+    }
+
+    // 把宇宙替换为录像开头的状态（保留物种注册表），回放从头开始。
+    // 确定性的录像打开确定性模式，保留当前的调度；否则切换到整图串行扫描
+    pub fn rewind(&mut self, universe: &mut Universe) -> Result<(), WorldFileError> {
+        let start = decode_world(&self.replay.world)?;
+        universe.reseed(self.replay.seed);
+        universe.deterministic = self.replay.deterministic;
+        if !self.replay.deterministic {
+            universe.schedule = TickSchedule::Serial;
+        }
+        replace_universe(universe, start);
+        universe.flush_undos();
+        self.cursor = 0;
+        self.ticks = 0;
+        Ok(())
+    }
+
+    pub fn tick_count(&self) -> usize {
+        self.replay.events.iter().filter(|event| matches!(event, ReplayEvent::Tick { .. })).count()
+    }
+
+    // 执行事件直到（包括）下一个 Tick，返回是否执行了 Tick；Tick 之后校验和不一致时返回失步
+    pub fn step(&mut self, universe: &mut Universe) -> Result<bool, ReplayDesync> {
+        while let Some(event) = self.replay.events.get(self.cursor) {
+            self.cursor += 1;
+            let Some(expected) = apply_event(universe, event) else {
+                continue;
+            };
+            self.ticks += 1;
+            let actual = universe.checksum();
+            if actual != expected {
+                return Err(ReplayDesync { tick: self.ticks, expected, actual });
+            }
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+// 执行一条事件，是 Tick 时返回录像里记录的校验和
+fn apply_event(universe: &mut Universe, event: &ReplayEvent) -> Option<u64> {
+    match event {
+        ReplayEvent::Tick { checksum } => {
+            universe.tick();
+            return Some(*checksum);
+        }
+        ReplayEvent::Paint { x, y, size, species } => universe.paint(*x, *y, *size, *species),
+        ReplayEvent::Erase { x, y, size } => universe.paint(*x, *y, *size, Species::Empty),
+        ReplayEvent::Place { x, y, species } => universe.place(*x, *y, *species),
+        ReplayEvent::Reset => universe.reset(),
+        ReplayEvent::PushUndo => universe.push_undo(),
+        ReplayEvent::Undo => universe.pop_undo(),
+        ReplayEvent::Redo => universe.redo(),
+        ReplayEvent::Load(world) => match decode_world(world) {
+            Ok(loaded) => replace_universe(universe, loaded),
+            Err(err) => eprintln!("replay contains a bad world: {}", err),
+        },
+        ReplayEvent::Emitters(emitters) => universe.set_emitters(emitters.clone()),
+        ReplayEvent::Material(material) => {
+            let mut registry = (*universe.registry).clone();
+            registry.set_custom_material(material.clone());
+            universe.set_registry(registry);
+        }
+    }
+    None
+}
+
+// 录像插件：
+//   --record <path>  启动后立即开始录制，退出时保存
+//   --replay <path>  启动时加载录像，由 SimulationPlugin 按当前速度逐 tick 回放（空格暂停、句号单步），
+//                    回放期间不接受编辑输入和 Ctrl+R，也不播放开场动画
+//   Ctrl+R           开始/停止录制，停止时保存到录像路径
+pub struct ReplayPlugin;
+
+#[derive(Resource)]
+pub struct ReplayPath(pub PathBuf);
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, replay_on_startup.after(load_svg_on_startup))
+            .add_systems(Update, record_input_system.run_if(not_replaying))
+            .add_systems(Last, save_recording_on_exit);
+    }
+}
+
+fn replay_on_startup(
+    mut commands: Commands,
+    args: Res<CliArgs>,
+    mut universe: ResMut<Universe>,
+) {
+    let path = args.record.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_REPLAY_PATH));
+    commands.insert_resource(ReplayPath(path));
+
+    if let Some(path) = args.replay.as_ref() {
+        match player_from_path(&mut universe, path) {
+            Ok(player) => {
+                println!("replaying {} ({} ticks)", path.display(), player.tick_count());
+                commands.insert_resource(player);
+            }
+            Err(err) => eprintln!("failed to load replay {}: {}", path.display(), err),
+        }
+    } else if args.record.is_some() {
+        start_recording(&mut universe);
+        println!("recording started");
+    }
+}
+
+// 运行条件：回放时不接受会修改宇宙的输入（画笔、印章、发射器、存档、材质），否则回放会失步；
+// 也不能开始录制，否则回放出来的编辑会被再录一遍
+pub fn not_replaying(player: Option<Res<ReplayPlayer>>) -> bool {
+    player.is_none()
+}
+
+// 加载录像并把宇宙倒回到录像开头
+pub fn player_from_path(universe: &mut Universe, path: &Path) -> Result<ReplayPlayer, WorldFileError> {
+    let mut player = ReplayPlayer::new(load_replay(path)?);
+    player.rewind(universe)?;
+    Ok(player)
+}
+
+fn record_input_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    path: Option<Res<ReplayPath>>,
+    mut universe: ResMut<Universe>,
+) {
+    let Some(path) = path else {
+        return;
+    };
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight, KeyCode::SuperLeft, KeyCode::SuperRight]);
+    if !ctrl || !keys.just_pressed(KeyCode::KeyR) {
+        return;
+    }
+    match stop_recording(&mut universe) {
+        Some(recorder) => save_recording_with_log(&recorder, &path.0),
+        None => {
+            start_recording(&mut universe);
+            println!("recording started");
+        }
+    }
+}
+
+fn save_recording_on_exit(
+    mut exit: EventReader<AppExit>,
+    path: Option<Res<ReplayPath>>,
+    mut universe: ResMut<Universe>,
+) {
+    if exit.read().next().is_none() {
+        return;
+    }
+    if let (Some(path), Some(recorder)) = (path, stop_recording(&mut universe)) {
+        save_recording_with_log(&recorder, &path.0);
+    }
+}
+
+fn save_recording_with_log(recorder: &Recorder, path: &Path) {
+    match save_replay(recorder, path) {
+        Ok(()) => println!("saved {} replay events to {}", recorder.len(), path.display()),
+        Err(err) => eprintln!("failed to save replay {}: {}", path.display(), err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 录一段包含编辑、撤销、发射器和材质重新加载的会话
+    fn record_session() -> Recorder {
+        let mut universe = Universe::with_seed(64, 48, 5);
+        start_recording(&mut universe);
+        universe.paint(20, 10, 6, Species::Sand);
+        universe.push_undo();
+        universe.paint(40, 10, 5, Species::Water);
+        for _ in 0..5 {
+            universe.tick();
+        }
+        universe.pop_undo();
+        universe.place(30, 30, Species::X);
+        let mut registry = (*universe.registry).clone();
+        registry.set_custom_material(CustomMaterial { name: "Slime".into(), gravity: -1, ..CustomMaterial::default() });
+        universe.set_registry(registry);
+        universe.set_emitters(vec![Emitter::parse("sand:0.5@top:10:4", &universe.registry).unwrap()]);
+        for _ in 0..5 {
+            universe.tick();
+        }
+        stop_recording(&mut universe).unwrap()
+    }
+
+    fn play(replay: Replay) -> Result<(Universe, usize), ReplayDesync> {
+        let mut universe = Universe::with_seed(10, 10, 1);
+        let mut player = ReplayPlayer::new(replay);
+        player.rewind(&mut universe).unwrap();
+        let mut ticks = 0;
+        while player.step(&mut universe)? {
+            ticks += 1;
+        }
+        Ok((universe, ticks))
+    }
+
+    #[test]
+    fn replay_round_trips() {
+        let recorder = record_session();
+        let replay = decode_replay(&recorder.encode()).unwrap();
+        assert_eq!(replay.seed, recorder.seed);
+        assert_eq!(replay.deterministic, recorder.deterministic);
+        assert_eq!(replay.world, recorder.world);
+        assert_eq!(replay.events, recorder.events);
+        assert!(replay.events.iter().any(|event| matches!(event, ReplayEvent::Material(m) if m.name == "Slime")));
+    }
+
+    #[test]
+    fn replay_reproduces_material_reloads() {
+        let recorder = record_session();
+        let (universe, ticks) = play(decode_replay(&recorder.encode()).unwrap()).unwrap();
+        assert_eq!(ticks, 10);
+        assert_eq!(universe.registry.custom_material().name, "Slime");
+    }
+
+    #[test]
+    fn replay_detects_checksum_mismatch() {
+        let mut recorder = record_session();
+        let tick = recorder.events.iter().position(|event| matches!(event, ReplayEvent::Tick { .. })).unwrap();
+        recorder.events[tick] = ReplayEvent::Tick { checksum: 0 };
+        let Err(desync) = play(decode_replay(&recorder.encode()).unwrap()) else {
+            panic!("the tampered checksum was not detected");
+        };
+        assert_eq!((desync.tick, desync.expected), (1, 0));
+    }
+}
@@ -0,0 +1,122 @@
+// 游程编码（RLE），用于压缩撤销快照和存档中的细胞数据。
+// 连续相同的元素只存一份（repeat run），其余元素原样存放（literal run），
+// 所以大片空白或同一物种的区域几乎不占空间，而随机的区域最多只多出每段 4 字节的开销。
+
+// runs 中最高位为 1 表示重复段，其余位是长度
+const REPEAT_FLAG: u32 = 1 << 31;
+// 至少连续这么多个相同元素才编码为重复段
+const MIN_REPEAT: usize = 3;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Rle<T> {
+    pub(crate) runs: Vec<u32>,
+    pub(crate) values: Vec<T>,
+    pub(crate) len: usize,
+}
+
+impl<T: Copy + PartialEq> Rle<T> {
+    pub fn encode(items: &[T]) -> Self {
+        let mut rle = Rle {
+            runs: Vec::new(),
+            values: Vec::new(),
+            len: items.len(),
+        };
+        let mut literal_start = 0;
+        let mut i = 0;
+        while i < items.len() {
+            let mut run = 1;
+            while i + run < items.len() && items[i + run] == items[i] && run < (REPEAT_FLAG - 1) as usize {
+                run += 1;
+            }
+            if run >= MIN_REPEAT {
+                rle.push_literal(&items[literal_start..i]);
+                rle.runs.push(REPEAT_FLAG | run as u32);
+                rle.values.push(items[i]);
+                i += run;
+                literal_start = i;
+            } else {
+                i += run;
+            }
+        }
+        rle.push_literal(&items[literal_start..]);
+        rle
+    }
+
+    fn push_literal(&mut self, items: &[T]) {
+        if items.is_empty() {
+            return;
+        }
+        self.runs.push(items.len() as u32);
+        self.values.extend_from_slice(items);
+    }
+
+    // 检查 runs 和 values 是否正好解码出 len 个元素。
+    // 来自文件的 runs 不可信，每段最长 2^31，解码之前必须先检查，否则一个很小的损坏文件就能分配几 GB 内存。
+    pub fn is_valid(&self) -> bool {
+        let mut total: usize = 0;
+        let mut value_count: usize = 0;
+        for &run in self.runs.iter() {
+            let n = (run & !REPEAT_FLAG) as usize;
+            let Some(next) = total.checked_add(n).filter(|&next| next <= self.len) else {
+                return false;
+            };
+            total = next;
+            value_count += if run & REPEAT_FLAG != 0 { 1 } else { n };
+        }
+        total == self.len && value_count == self.values.len()
+    }
+
+    // 最多解码 len 个元素
+    pub fn decode_into(&self, out: &mut Vec<T>) {
+        out.clear();
+        out.reserve(self.len);
+        let mut values = self.values.iter();
+        for &run in self.runs.iter() {
+            let n = ((run & !REPEAT_FLAG) as usize).min(self.len - out.len());
+            if run & REPEAT_FLAG != 0 {
+                let Some(&v) = values.next() else { return };
+                out.extend(std::iter::repeat(v).take(n));
+            } else {
+                out.extend(values.by_ref().take(n).copied());
+            }
+        }
+    }
+
+    pub fn decode(&self) -> Vec<T> {
+        let mut out = Vec::new();
+        self.decode_into(&mut out);
+        out
+    }
+
+    // 解码后的元素个数
+    pub fn len(&self) -> usize {
+        self.len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_mixed_runs() {
+        let items = [0, 0, 0, 0, 1, 2, 3, 3, 4, 4, 4, 4, 4, 5];
+        let rle = Rle::encode(&items);
+        assert!(rle.is_valid());
+        assert_eq!(rle.len(), items.len());
+        assert_eq!(rle.decode(), items);
+        assert!(Rle::<u8>::encode(&[]).decode().is_empty());
+    }
+
+    #[test]
+    fn rejects_runs_that_do_not_match_len() {
+        let valid = Rle::encode(&[7u8; 10]);
+        let too_long = Rle { runs: vec![REPEAT_FLAG | 11], ..valid.clone() };
+        let huge = Rle { runs: vec![REPEAT_FLAG | (REPEAT_FLAG - 1), 3], ..valid.clone() };
+        let missing_values = Rle { runs: vec![10], ..valid.clone() };
+        assert!(valid.is_valid());
+        assert!(!too_long.is_valid());
+        assert!(!huge.is_valid());
+        assert!(!missing_values.is_valid());
+    }
+}
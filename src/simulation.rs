@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use crate::registry::SpeciesRegistry;
+use crate::replay::ReplayPlayer;
+use crate::universe::Universe;
+
+// 模拟插件：在 FixedUpdate 中推进 Universe（58Hz，由 main.rs 的 setup 设置）。
+// 空格切换运行/暂停，句号键在暂停时单步，+/- 调整每个固定步长执行的 tick 次数。
+// SpeciesRegistry 资源被修改后会同步到 Universe 内部使用的注册表。
+// 存在 ReplayPlayer 资源时，每个 tick 改为执行录像中的下一段事件，回放结束或失步后移除回放器。
+pub struct SimulationPlugin;
+
+// 每个固定步长最多执行的 tick 次数
+const MAX_TICKS_PER_STEP: u32 = 16;
+
+#[derive(Resource)]
+pub struct SimulationControl {
+    // 是否自动运行
+    pub running: bool,
+    // 暂停时请求单步，执行一次后自动清除
+    pub step_requested: bool,
+    // 每个固定步长执行的 tick 次数
+    pub ticks_per_step: u32,
+}
+
+impl Default for SimulationControl {
+    fn default() -> Self {
+        Self {
+            running: true,
+            step_requested: false,
+            ticks_per_step: 1,
+        }
+    }
+}
+
+impl SimulationControl {
+    pub fn toggle(&mut self) {
+        self.running = !self.running;
+    }
+
+    pub fn request_step(&mut self) {
+        self.step_requested = true;
+    }
+
+    pub fn set_ticks_per_step(&mut self, ticks: u32) {
+        self.ticks_per_step = ticks.clamp(1, MAX_TICKS_PER_STEP);
+    }
+}
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<SimulationControl>()
+            .init_resource::<SpeciesRegistry>()
+            .add_systems(Update, simulation_input_system)
+            .add_systems(FixedUpdate, (
+                sync_species_registry,
+                simulation_tick_system.after(sync_species_registry),
+            ));
+    }
+}
+
+fn simulation_input_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut control: ResMut<SimulationControl>,
+) {
+    if keys.just_pressed(KeyCode::Space) {
+        control.toggle();
+        println!("simulation running: {}", control.running);
+    }
+    if keys.just_pressed(KeyCode::Period) && !control.running {
+        control.request_step();
+    }
+    if keys.just_pressed(KeyCode::Equal) || keys.just_pressed(KeyCode::NumpadAdd) {
+        let ticks = control.ticks_per_step + 1;
+        control.set_ticks_per_step(ticks);
+        println!("ticks per step: {}", control.ticks_per_step);
+    }
+    if keys.just_pressed(KeyCode::Minus) || keys.just_pressed(KeyCode::NumpadSubtract) {
+        let ticks = control.ticks_per_step.saturating_sub(1);
+        control.set_ticks_per_step(ticks);
+        println!("ticks per step: {}", control.ticks_per_step);
+    }
+}
+
+fn sync_species_registry(
+    registry: Res<SpeciesRegistry>,
+    mut universe: ResMut<Universe>,
+) {
+    if registry.is_changed() {
+        universe.set_registry(registry.clone());
+    }
+}
+
+fn simulation_tick_system(
+    mut commands: Commands,
+    mut control: ResMut<SimulationControl>,
+    mut universe: ResMut<Universe>,
+    mut player: Option<ResMut<ReplayPlayer>>,
+) {
+    let ticks = if control.running {
+        control.ticks_per_step
+    } else if control.step_requested {
+        // 单步只推进一次 tick，与 ticks_per_step 无关
+        control.step_requested = false;
+        1
+    } else {
+        0
+    };
+    for _ in 0..ticks {
+        let Some(player) = player.as_mut() else {
+            universe.tick();
+            continue;
+        };
+        match player.step(&mut universe) {
+            Ok(true) => {}
+            Ok(false) => {
+                println!("replay finished");
+                commands.remove_resource::<ReplayPlayer>();
+                break;
+            }
+            Err(desync) => {
+                eprintln!("{}", desync);
+                commands.remove_resource::<ReplayPlayer>();
+                break;
+            }
+        }
+    }
+}
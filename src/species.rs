@@ -2,9 +2,9 @@ use super::utils::*;
 
 
 // use std::cmp;
-use std::mem;
+use std::convert::TryFrom;
 use bytemuck::{Pod, Zeroable};
-use rand::Rng;
+use crate::temperature::*;
 use crate::universe::{Cell, SandApi, Wind, EMPTY_CELL};
 // use web_sys::console;
 
@@ -17,60 +17,85 @@ pub enum Species {
     Wall = 1,
     Sand = 2,
     Water = 3,
-    // X = 21,
+    X = 21,
     Stone = 13,
     Ice = 9,
     Gas = 4,
     Cloner = 5,
-    // Sink = 10,
+    Sink = 10,
     Mite = 15,
     Wood = 7,
     Plant = 11,
     Fungus = 18,
     Seed = 19,
+    Snow = 20,
+    Lightning = 22,
+    Steam = 23,
+    Gunpowder = 24,
+    Smoke = 25,
+    Ash = 26,
     Fire = 6,
     Lava = 8,
     Acid = 12,
     Dust = 14,
     Oil = 16,
     Rocket = 17,
+    // 未知物种（sandspiel 里的 "BELP"），所有无法识别的字节都解码成它
+    Unknown = 255,
 }
 
-impl Species {
-    // Species::update 方法是一个分发器，根据不同的物种类型调用不同的更新函数。每个物种的行为是由其对应的 update_* 方法决定的。
-    pub fn update(&self, cell: Cell, api: SandApi) {
-        match self {
-            Species::Empty => {}
-            Species::Wall => {}
-            Species::Sand => update_sand(cell, api),
-            Species::Dust => update_dust(cell, api),
-            Species::Water => update_water(cell, api),
-            Species::Stone => update_stone(cell, api),
-            Species::Gas => update_gas(cell, api),
-            Species::Cloner => update_cloner(cell, api),
-            Species::Rocket => update_rocket(cell, api),
-            Species::Fire => update_fire(cell, api),
-            Species::Wood => update_wood(cell, api),
-            Species::Lava => update_lava(cell, api),
-            Species::Ice => update_ice(cell, api),
-            // Species::Snow => update_ice(cell, api),
-            //lightning
-            // Species::Sink => update_sink(cell, api),
-            Species::Plant => update_plant(cell, api),
-            Species::Acid => update_acid(cell, api),
-            Species::Mite => update_mite(cell, api),
-            Species::Oil => update_oil(cell, api),
-            Species::Fungus => update_fungus(cell, api),
-            Species::Seed => update_seed(cell, api),
-            // Species::X => update_x(cell, api),
-        }
-    }
-}
 impl Into<u8> for Species {
     fn into(self) -> u8 {
         self as u8
     }
 }
+
+// 字节到物种的检查转换，不认识的 id 返回 Err(id)
+impl TryFrom<u8> for Species {
+    type Error = u8;
+
+    fn try_from(id: u8) -> Result<Self, Self::Error> {
+        let species = match id {
+            0 => Species::Empty,
+            1 => Species::Wall,
+            2 => Species::Sand,
+            3 => Species::Water,
+            4 => Species::Gas,
+            5 => Species::Cloner,
+            6 => Species::Fire,
+            7 => Species::Wood,
+            8 => Species::Lava,
+            9 => Species::Ice,
+            10 => Species::Sink,
+            11 => Species::Plant,
+            12 => Species::Acid,
+            13 => Species::Stone,
+            14 => Species::Dust,
+            15 => Species::Mite,
+            16 => Species::Oil,
+            17 => Species::Rocket,
+            18 => Species::Fungus,
+            19 => Species::Seed,
+            20 => Species::Snow,
+            21 => Species::X,
+            22 => Species::Lightning,
+            23 => Species::Steam,
+            24 => Species::Gunpowder,
+            25 => Species::Smoke,
+            26 => Species::Ash,
+            255 => Species::Unknown,
+            _ => return Err(id),
+        };
+        Ok(species)
+    }
+}
+
+impl Species {
+    // 从 cell.rb、存档或 GPU 回读的字节解码物种，未知 id 统一映射为 Species::Unknown
+    pub fn from_id(id: u8) -> Species {
+        Species::try_from(id).unwrap_or(Species::Unknown)
+    }
+}
 // update_sand 方法处理沙子的行为。沙子会根据周围环境进行下落：
 //
 // 如果下方是空的，沙子会下落。
@@ -100,6 +125,20 @@ pub fn update_sand(cell: Cell, mut api: SandApi) {
     }
 }
 
+// 火药爆炸的半径，超出了 SandApi 的 ±2 范围
+pub const GUNPOWDER_BLAST_RADIUS: i32 = 6;
+
+// 火药（Gunpowder）：像沙子一样下落、堆积。温度达到 GUNPOWDER_IGNITE_TEMP 时爆炸，
+// 被闪电击中时先变成火，再由火的热量引爆。爆炸只是请求（SandApi::explode），本 tick 结束时才炸开（见 blast.rs）。
+pub fn update_gunpowder(cell: Cell, mut api: SandApi) {
+    if api.get_temp() >= GUNPOWDER_IGNITE_TEMP {
+        api.set(0, 0, EMPTY_CELL);
+        api.explode(GUNPOWDER_BLAST_RADIUS);
+        return;
+    }
+    update_sand(cell, api);
+}
+
 // update_dust 方法描述了尘土的行为：
 //
 // 如果流体的压力大于 120，尘土会变为火，并生成一个风流体（Wind）。
@@ -185,9 +224,42 @@ pub fn update_stone(cell: Cell, mut api: SandApi) {
     }
 }
 
+// 水冷到冰点以下时每 tick 结冰的概率为 1 / WATER_FREEZE_CHANCE，冰从冷源往外一点点长
+const WATER_FREEZE_CHANCE: i32 = 8;
+
 // 在一个细胞自动机的模拟中处理水的行为，可能是用来模拟沙盒游戏或者物理引擎中的流体行为。它通过不同的条件和随机行为来操控当前水的细胞及其邻近的细胞。
 // cell: 当前的水细胞。  api: 一个引用 SandApi 的对象，提供了随机数生成和操作邻近细胞的方法。
 pub fn update_water(cell: Cell, mut api: SandApi) {
+    // 达到沸点时沸腾成蒸汽，沸腾吸热，格子回到室温
+    if api.get_temp() >= WATER_BOIL_TEMP {
+        let rb = STEAM_COOL_TICKS + api.rand_int(STEAM_COOL_TICKS as i32) as u8;
+        api.set(
+            0,
+            0,
+            Cell {
+                species: Species::Steam,
+                ra: cell.ra,
+                rb,
+                clock: 0,
+            },
+        );
+        api.set_temp(AMBIENT_TEMP);
+        return;
+    }
+    // 冷到冰点以下时结冰
+    if api.get_temp() <= WATER_FREEZE_TEMP && api.once_in(WATER_FREEZE_CHANCE) {
+        api.set(
+            0,
+            0,
+            Cell {
+                species: Species::Ice,
+                ra: cell.ra,
+                rb: 0,
+                clock: 0,
+            },
+        );
+        return;
+    }
     let mut dx = api.rand_dir();  // 随机方向
     let below = api.get(0, 1);    // 获取下方细胞
     let dx1 = api.get(dx, 1);     // 获取斜下方细胞
@@ -530,6 +602,99 @@ pub fn update_gas(cell: Cell, mut api: SandApi) {
     }
 }
 
+// 蒸汽的 rb 初始值的下限，实际值在 STEAM_COOL_TICKS..2 * STEAM_COOL_TICKS 之间
+const STEAM_COOL_TICKS: u8 = 90;
+
+// 蒸汽（Steam）：很轻，会被风吹走，向上飘，穿过水时像气泡一样上浮。
+// rb 是剩余的热量，所在格子的温度低于沸点时每 tick 减一，减到 0 时冷凝成水，ra 沿用水的 ra。
+pub fn update_steam(cell: Cell, mut api: SandApi) {
+    let mut rb = cell.rb;
+    if api.get_temp() < WATER_BOIL_TEMP {
+        rb = rb.saturating_sub(1);
+    }
+    if rb == 0 {
+        api.set(
+            0,
+            0,
+            Cell {
+                species: Species::Water,
+                ra: cell.ra,
+                rb: 0,
+                clock: 0,
+            },
+        );
+        return;
+    }
+    let cell = Cell { rb, ..cell };
+
+    let dx = api.rand_dir();
+    let above = api.get(0, -1);
+    if above.species == Species::Empty || above.species == Species::Water {
+        api.set(0, 0, above);
+        api.set(0, -1, cell);
+    } else if api.get(dx, -1).species == Species::Empty {
+        api.set(0, 0, EMPTY_CELL);
+        api.set(dx, -1, cell);
+    } else if api.get(dx, 0).species == Species::Empty {
+        api.set(0, 0, EMPTY_CELL);
+        api.set(dx, 0, cell);
+    } else {
+        api.set(0, 0, cell);
+    }
+}
+
+// 烟的寿命下限，实际寿命在 SMOKE_LIFETIME..2 * SMOKE_LIFETIME 之间
+const SMOKE_LIFETIME: u8 = 60;
+
+// 烟（Smoke）：很轻，会被风吹走，向上飘并慢慢消散。
+// rb 是剩余寿命，新产生的烟 rb 为 0，第一次更新时才抽取寿命，所以爆炸等地方直接放一个 rb 为 0 的烟即可。
+pub fn update_smoke(cell: Cell, mut api: SandApi) {
+    let rb = match cell.rb {
+        0 => SMOKE_LIFETIME + api.rand_int(SMOKE_LIFETIME as i32) as u8,
+        1 => {
+            api.set(0, 0, EMPTY_CELL);
+            return;
+        }
+        rb => rb - 1,
+    };
+    let cell = Cell { rb, ..cell };
+
+    let dx = api.rand_dir();
+    if api.get(dx, -1).species == Species::Empty {
+        api.set(0, 0, EMPTY_CELL);
+        api.set(dx, -1, cell);
+    } else if api.get(dx, 0).species == Species::Empty {
+        api.set(0, 0, EMPTY_CELL);
+        api.set(dx, 0, cell);
+    } else {
+        api.set(0, 0, cell);
+    }
+}
+
+
+// 灰烬（Ash）：木头和植物燃尽后留下的，像很轻的沙子一样下落、堆积，浮在水面上，风容易把它吹走。
+// 种子落在灰烬上会长得更高（见 update_seed）。
+pub fn update_ash(cell: Cell, mut api: SandApi) {
+    let dx = api.rand_dir_2();
+
+    let nbr = api.get(0, 1);
+    if nbr.species == Species::Empty {
+        api.set(0, 0, EMPTY_CELL);
+        api.set(0, 1, cell);
+    } else if api.get(dx, 1).species == Species::Empty {
+        api.set(0, 0, EMPTY_CELL);
+        api.set(dx, 1, cell);
+    } else if nbr.species == Species::Gas
+        || nbr.species == Species::Smoke
+        || nbr.species == Species::Steam
+    {
+        api.set(0, 0, nbr);
+        api.set(0, 1, cell);
+    } else {
+        api.set(0, 0, cell);
+    }
+}
+
 
 // “克隆体”（Cloner）的物质在模拟环境中的行为。克隆体根据周围的环境不断复制自己或尝试克隆其他物质。
 // 1 克隆体会根据周围的细胞类型和状态，克隆出新的细胞。
@@ -540,7 +705,7 @@ pub fn update_gas(cell: Cell, mut api: SandApi) {
 // 多样化的克隆条件：可以根据 generation 或 ra 值调整克隆体的克隆行为，使其更加有趣和复杂。
 // 克隆体之间的竞争或互动：可以加入克隆体之间的互动规则，比如克隆体相互之间的冲突或竞争。
 pub fn update_cloner(cell: Cell, mut api: SandApi) {
-    let mut clone_species = unsafe { mem::transmute(cell.rb as u8) };  // 将 `cell.rb` 转换为物种类型
+    let mut clone_species = Species::from_id(cell.rb);  // 将 `cell.rb` 转换为物种类型
     let g = api.universe.generation;  // 获取当前的宇宙代数
     // 这部分代码是用来遍历克隆体周围的 3x3 区域（包括当前位置）。
     // dx 和 dy 分别代表 x 和 y 方向上的偏移，范围从 -1 到 1。
@@ -609,6 +774,22 @@ pub fn update_cloner(cell: Cell, mut api: SandApi) {
 }
 
 
+// 水槽（Sink）：静止不动，吞掉接触到的所有相邻细胞（周围 3x3），墙和其他水槽除外。
+// 用来搭建排水口和传送带谜题，流进来的水、沙子等都会消失。
+pub fn update_sink(_cell: Cell, mut api: SandApi) {
+    for dx in [-1, 0, 1] {
+        for dy in [-1, 0, 1] {
+            let nbr_species = api.get(dx, dy).species;
+            if nbr_species != Species::Empty
+                && nbr_species != Species::Wall
+                && nbr_species != Species::Sink
+            {
+                api.set(dx, dy, EMPTY_CELL);
+            }
+        }
+    }
+}
+
 // 一个具有复杂行为的“火箭” (Rocket) 物质更新机制，涉及火箭的多个阶段（如初始化、待机、发射、飞行）。
 // 其状态通过 ra 和 rb 属性来控制，每个阶段的行为不同，且会根据周围环境的情况进行相应的调整。
 
@@ -646,7 +827,7 @@ pub fn update_rocket(cell: Cell, mut api: SandApi) {
     // 这里根据 cell.rb 的值来确定火箭的物种类型。如果 cell.rb 不为 100，则将 cell.rb 转换为一个物种（Species）。
     // 如果 cell.rb 为 100，则设置为沙子 (Species::Sand)。
     let clone_species = if cell.rb != 100 {
-        unsafe { mem::transmute(cell.rb as u8) }
+        Species::from_id(cell.rb)
     } else {
         Species::Sand
     };
@@ -741,8 +922,10 @@ pub fn update_rocket(cell: Cell, mut api: SandApi) {
             || nbr.species == Species::Fire
             || nbr.species == Species::Rocket
         {
-            api.set(0, 0, Cell::new(clone_species));
-            api.set(0, dy, Cell::new(clone_species));
+            let cloned = api.new_cell(clone_species);
+            api.set(0, 0, cloned);
+            let cloned = api.new_cell(clone_species);
+            api.set(0, dy, cloned);
 
             let (ndx, ndy) = match api.rand_int(100) % 5 {
                 0 => adjacency_left((dx, dy)),
@@ -775,6 +958,9 @@ pub fn update_rocket(cell: Cell, mut api: SandApi) {
 // 风的影响：风会对火焰的扩散产生影响，每次火焰更新时，都会设置风的压力和密度，模拟火焰受到风力的推动。
 // 随机性：火焰的扩散和降解具有很强的随机性，尤其是在与周围物质互动时，每次更新都会基于随机方向来决定火焰的行为。
 
+// 燃尽的火焰留下烟的概率为 1 / FIRE_SMOKE_CHANCE
+const FIRE_SMOKE_CHANCE: i32 = 3;
+
 pub fn update_fire(cell: Cell, mut api: SandApi) {
     // 1. 火焰降解
     // 获取当前火焰细胞的 ra 值（表示火焰的强度或阶段）。
@@ -782,7 +968,7 @@ pub fn update_fire(cell: Cell, mut api: SandApi) {
     // api.rand_dir() 返回一个随机的方向值（可能是 -1、0、1），因此这个变化是有随机性的。
     let ra = cell.ra;
     let mut degraded = cell.clone();
-    degraded.ra = ra - (2 + api.rand_dir()) as u8;
+    degraded.ra = ra.wrapping_sub((2 + api.rand_dir()) as u8);
 
     // 2. 随机选择一个方向进行扩散
     // api.rand_vec() 返回一个随机的二维向量 (dx, dy)，用来表示火焰扩散的方向。
@@ -823,10 +1009,18 @@ pub fn update_fire(cell: Cell, mut api: SandApi) {
         });
     }
     // 5. 火焰与水或空白区域的交互
-    // 如果火焰的强度 ra 小于 5，或者扩散到的地方是水 (Species::Water)，则火焰会被熄灭（设置为空白细胞 EMPTY_CELL）。
+    // 如果火焰的强度 ra 小于 5，火焰燃尽，有一定概率留下一缕烟（Species::Smoke）。
+    // 如果扩散到的地方是水 (Species::Water)，则火焰会被熄灭（设置为空白细胞 EMPTY_CELL）。
     // 如果扩散到的地方是空白 (Species::Empty)，则将当前火焰置为空白，并将降解后的火焰放置到新的位置。
     // 如果扩散到的地方不是空白且也不是水，则将火焰的降解状态放置在当前位置。
-    if ra < 5 || api.get(dx, dy).species == Species::Water {
+    if ra < 5 {
+        if api.once_in(FIRE_SMOKE_CHANCE) {
+            let smoke = api.new_cell(Species::Smoke);
+            api.set(0, 0, smoke);
+        } else {
+            api.set(0, 0, EMPTY_CELL);
+        }
+    } else if api.get(dx, dy).species == Species::Water {
         api.set(0, 0, EMPTY_CELL);
     } else if api.get(dx, dy).species == Species::Empty {
         api.set(0, 0, EMPTY_CELL);
@@ -836,6 +1030,95 @@ pub fn update_fire(cell: Cell, mut api: SandApi) {
     }
 }
 
+// 闪电留下的轨迹持续的 tick 数
+const LIGHTNING_TRAIL_TICKS: u8 = 6;
+
+// 闪电（Lightning）：rb == 0 的是闪电头，ra 是剩余能量；rb > 0 的是闪电头走过后留下的轨迹，rb 是剩余的 tick 数。
+// 闪电头每 tick 向下走两步，穿过空气时留下轨迹，每步消耗一点能量，能量耗尽或被挡住时变成轨迹。
+// 碰到木头、植物、气体、尘土会点燃它们；碰到水会导电：与水交换位置继续前进，
+// 在水里被挡住时向任意方向的水扩散，所以会沿着水面传到岸边的植物上。
+pub fn update_lightning(cell: Cell, mut api: SandApi) {
+    if cell.rb > 0 {
+        if cell.rb == 1 {
+            api.set(0, 0, EMPTY_CELL);
+        } else {
+            api.set(0, 0, Cell { rb: cell.rb - 1, ..cell });
+        }
+        return;
+    }
+
+    let mut head = cell;
+    let mut pos = (0, 0);
+    for _ in 0..2 {
+        match lightning_step(&mut api, pos, head) {
+            Some((next, next_head)) => {
+                pos = next;
+                head = next_head;
+            }
+            None => return,
+        }
+    }
+}
+
+fn is_flammable(species: Species) -> bool {
+    species == Species::Wood
+        || species == Species::Plant
+        || species == Species::Gas
+        || species == Species::Dust
+        || species == Species::Gunpowder
+}
+
+// 闪电头从偏移 pos 前进一步，返回新的位置和闪电头；闪电结束时返回 None。
+// 两步之内偏移不会超出 SandApi 的 ±2 范围。
+fn lightning_step(api: &mut SandApi, pos: (i32, i32), head: Cell) -> Option<((i32, i32), Cell)> {
+    let trail = Cell {
+        rb: LIGHTNING_TRAIL_TICKS,
+        ..head
+    };
+    if head.ra < 5 {
+        api.set(pos.0, pos.1, trail);
+        return None;
+    }
+
+    let dx = api.rand_dir();
+    let mut target = (pos.0 + dx, pos.1 + 1);
+    let mut nbr = api.get(target.0, target.1);
+    // 被挡住时尝试沿着水传导
+    if nbr.species != Species::Empty && nbr.species != Species::Water && !is_flammable(nbr.species) {
+        let (vx, vy) = api.rand_vec_8();
+        target = (pos.0 + vx, pos.1 + vy);
+        nbr = api.get(target.0, target.1);
+    }
+
+    if nbr.species == Species::Empty {
+        api.set(pos.0, pos.1, trail);
+        let head = Cell { ra: head.ra - 1, ..head };
+        api.set(target.0, target.1, head);
+        Some((target, head))
+    } else if nbr.species == Species::Water {
+        api.set(pos.0, pos.1, nbr);
+        let head = Cell { ra: head.ra - 1, ..head };
+        api.set(target.0, target.1, head);
+        Some((target, head))
+    } else if is_flammable(nbr.species) {
+        api.set(
+            target.0,
+            target.1,
+            Cell {
+                species: Species::Fire,
+                ra: 150 + (head.ra / 10),
+                rb: 0,
+                clock: 0,
+            },
+        );
+        api.set(pos.0, pos.1, trail);
+        None
+    } else {
+        api.set(pos.0, pos.1, trail);
+        None
+    }
+}
+
 // 模拟“岩浆”（Lava）在沙盒模拟程序中的行为。它涉及了岩浆与周围环境的交互、流动以及与其他物质的反应（例如与水、火等的反应）
 pub fn update_lava(cell: Cell, mut api: SandApi) {
     //    1. 设置风力流体
@@ -870,7 +1153,7 @@ pub fn update_lava(cell: Cell, mut api: SandApi) {
 
     // 4. 与水的交互
     //  如果随机选择的格子是水（Species::Water），则岩浆与水发生反应，岩浆变成石头（Species::Stone），并将石头放置在当前格子。
-    // 同时，水被移除，设置为 EMPTY_CELL。
+    // 同时，水被汽化成蒸汽（Species::Steam）。
     //
     let sample = api.get(dx, dy);
     if sample.species == Species::Water {
@@ -884,7 +1167,16 @@ pub fn update_lava(cell: Cell, mut api: SandApi) {
                 clock: 0,
             },
         );
-        api.set(dx, dy, EMPTY_CELL);
+        api.set(
+            dx,
+            dy,
+            Cell {
+                species: Species::Steam,
+                ra: sample.ra,
+                rb: STEAM_COOL_TICKS,
+                clock: 0,
+            },
+        );
 
     //     5. 岩浆的移动
     // 接下来，岩浆尝试向周围的空白格子（Species::Empty）移动。如果周围的格子是空的，它会向该格子移动，否则保持当前位置。
@@ -915,9 +1207,9 @@ pub fn update_wood(cell: Cell, mut api: SandApi) {
     let nbr_species = api.get(dx, dy).species;
 
     // 1. 初始化与火或熔岩交互
-    // 这段代码首先判断木材（Wood）是否处于初始状态（rb == 0）。如果是并且它周围的格子是火（Fire）或熔岩（Lava），则木材将变为状态 Wood，并且将其 rb 设置为 90。
+    // 这段代码首先判断木材（Wood）是否处于初始状态（rb == 0）。如果是并且所在格子的温度达到着火点，则木材将变为状态 Wood，并且将其 rb 设置为 90。
     // ra 和 clock 由原始木材的属性继承
-    if rb == 0 && nbr_species == Species::Fire || nbr_species == Species::Lava {
+    if rb == 0 && api.get_temp() >= WOOD_IGNITE_TEMP {
         api.set(
             0,
             0,
@@ -980,16 +1272,16 @@ pub fn update_wood(cell: Cell, mut api: SandApi) {
             });
         }
 
-    //     5. 如果 rb 为 1，则变为空格
-    //     如果木材的 rb 为 1，则将其变为空格，并且 ra 设为原来木材的 ra，rb 设为 90，恢复为初始状态。
+    //     5. 如果 rb 为 1，则烧成灰
+    //     如果木材的 rb 为 1，则将其变为灰烬（Species::Ash），ra 沿用原来木材的 ra。
     } else if rb == 1 {
         api.set(
             0,
             0,
             Cell {
-                species: Species::Empty,
+                species: Species::Ash,
                 ra: cell.ra,
-                rb: 90,
+                rb: 0,
                 clock: 0,
             },
         );
@@ -997,10 +1289,6 @@ pub fn update_wood(cell: Cell, mut api: SandApi) {
 }
 // 你的代码用于模拟冰（Ice）的行为，主要涉及冰与火、岩浆、水等物质的互动。代码的逻辑比较清晰
 pub fn update_ice(cell: Cell, mut api: SandApi) {
-    let (dx, dy) = api.rand_vec();
-
-    let i = api.rand_int(100);
-
     let fluid = api.get_fluid();
 
     // // 如果流体压力大于120且有一定概率，冰会变成水
@@ -1018,9 +1306,9 @@ pub fn update_ice(cell: Cell, mut api: SandApi) {
         return;
     }
 
-    let nbr_species = api.get(dx, dy).species;
-    // // 如果邻居是火或岩浆，冰会变成水
-    if nbr_species == Species::Fire || nbr_species == Species::Lava {
+    // 冰本身是冷源（注册表里的 fixed_temp），挨着的水冷到冰点以下会自己结冰（见 update_water）。
+    // 温度超过融点时冰会变成水
+    if api.get_temp() > ICE_MELT_TEMP {
         api.set(
             0,
             0,
@@ -1031,21 +1319,68 @@ pub fn update_ice(cell: Cell, mut api: SandApi) {
                 clock: 0,
             },
         );
-    } else if nbr_species == Species::Water && i < 7 {
-        // 如果邻居是水且随机条件成立，冰会变成冰块
+        // 融化吸热，格子回到室温
+        api.set_temp(AMBIENT_TEMP);
+    }
+}
+
+
+// 雪每 tick 压实成冰的概率为 1 / SNOW_COMPACT_CHANCE
+const SNOW_COMPACT_CHANCE: i32 = 200;
+
+// 雪（Snow）：很轻，容易被风吹走，下落速度只有沙子的一半并且会左右飘。
+// 温度超过融点时融化成水；上面压着至少两格不比雪轻的东西时，会慢慢压实成冰。
+pub fn update_snow(cell: Cell, mut api: SandApi) {
+    if api.get_temp() > SNOW_MELT_TEMP {
         api.set(
-            dx,
-            dy,
+            0,
+            0,
+            Cell {
+                species: Species::Water,
+                ra: cell.ra,
+                rb: 0,
+                clock: 0,
+            },
+        );
+        api.set_temp(AMBIENT_TEMP);
+        return;
+    }
+
+    let above = api.get(0, -1).species;
+    let above2 = api.get(0, -2).species;
+    let below = api.get(0, 1).species;
+    let registry = &api.universe.registry;
+    let snow_density = registry.density(Species::Snow);
+    let pressed = [above, above2]
+        .iter()
+        .all(|&s| s != Species::Empty && registry.density(s) >= snow_density);
+    if pressed && below != Species::Empty && api.once_in(SNOW_COMPACT_CHANCE) {
+        api.set(
+            0,
+            0,
             Cell {
                 species: Species::Ice,
                 ra: cell.ra,
-                rb: cell.rb,
+                rb: 0,
                 clock: 0,
             },
         );
+        return;
     }
-}
 
+    // 飘落：一半的 tick 不动，下落时随机偏向一侧
+    if api.once_in(2) {
+        return;
+    }
+    let dx = api.rand_dir();
+    if api.get(dx, 1).species == Species::Empty {
+        api.set(0, 0, EMPTY_CELL);
+        api.set(dx, 1, cell);
+    } else if below == Species::Empty {
+        api.set(0, 0, EMPTY_CELL);
+        api.set(0, 1, cell);
+    }
+}
 
 // 涉及两种不同物质（植物 Plant 和种子 Seed）的行为逻辑
 
@@ -1053,14 +1388,14 @@ pub fn update_ice(cell: Cell, mut api: SandApi) {
 pub fn update_plant(cell: Cell, mut api: SandApi) {
 
     // 植物与火焰或岩浆的交互：
-    // 如果植物周围有火焰（Fire）或岩浆（Lava），并且植物的rb为0，它会变成一个新的植物，ra保持不变，rb设置为20（表示一些生长状态）。
+    // 如果植物所在格子的温度达到着火点，并且植物的rb为0，它会变成一个新的植物，ra保持不变，rb设置为20（表示一些生长状态）。
     let rb = cell.rb;
 
     let mut i = api.rand_int(100);
     let (dx, dy) = api.rand_vec();
 
     let nbr_species = api.get(dx, dy).species;
-    if rb == 0 && nbr_species == Species::Fire || nbr_species == Species::Lava {
+    if rb == 0 && api.get_temp() >= PLANT_IGNITE_TEMP {
         api.set(
             0,
             0,
@@ -1120,7 +1455,7 @@ pub fn update_plant(cell: Cell, mut api: SandApi) {
     // 4 植物的生命周期：
     //
     // 如果植物的rb大于1，它会减少rb并产生火焰（Fire）或者将植物的ra调整为50，如果附近是水。
-    // 如果rb为1，植物会被清除（设置为空）。
+    // 如果rb为1，植物烧尽，一半的概率留下灰烬（Ash），否则被清除（设置为空）。
     if rb > 1 {
         api.set(
             0,
@@ -1157,7 +1492,12 @@ pub fn update_plant(cell: Cell, mut api: SandApi) {
             )
         }
     } else if rb == 1 {
-        api.set(0, 0, EMPTY_CELL);
+        if api.once_in(2) {
+            let ash = api.new_cell(Species::Ash);
+            api.set(0, 0, ash);
+        } else {
+            api.set(0, 0, EMPTY_CELL);
+        }
     }
 
     //  5 植物繁殖：
@@ -1169,9 +1509,7 @@ pub fn update_plant(cell: Cell, mut api: SandApi) {
         && api.get(-1, 1).species != Species::Plant
     {
         if api.get(0, 1).species == Species::Empty {
-            let mut rng = rand::thread_rng();
-
-            let i = generate_random()  as i32;
+            let i = generate_random(&mut api) as i32;
             let dec = api.rand_int(30) - 20;
             if (i + ra as i32) > 165 {
                 api.set(
@@ -1196,18 +1534,21 @@ pub fn update_plant(cell: Cell, mut api: SandApi) {
     }
 }
 
+// 种子落在灰烬上时 ra 的增量
+const ASH_FERTILIZER: u8 = 40;
+
 // 描述了种子（Seed）的行为逻辑。它实现了种子从空中掉落、与周围物质的互动以及生长和扩展等行为。
 pub fn update_seed(cell: Cell, mut api: SandApi) {
     // 1 火焰与岩浆的处理：
     //
-    // 如果种子附近有火焰（Fire）或岩浆（Lava），种子会变为火焰（Fire），并且其属性被设置为 ra: 5，rb: 0。
+    // 如果种子所在格子的温度达到着火点，种子会变为火焰（Fire），并且其属性被设置为 ra: 5，rb: 0。
     let rb = cell.rb;
     let ra = cell.ra;
 
     let (dx, dy) = api.rand_vec();
 
     let nbr_species = api.get(dx, dy).species;
-    if nbr_species == Species::Fire || nbr_species == Species::Lava {
+    if api.get_temp() >= SEED_IGNITE_TEMP {
         api.set(
             0,
             0,
@@ -1224,7 +1565,8 @@ pub fn update_seed(cell: Cell, mut api: SandApi) {
     // 2 种子掉落逻辑：
     //
     // 如果种子正在掉落（rb == 0），它会根据周围的环境进行调整：
-    // 如果种子下面是沙子（Sand）、植物（Plant）或真菌（Fungus），它会停止掉落并生成一个新的生命值（rb）。
+    // 如果种子下面是沙子（Sand）、植物（Plant）、真菌（Fungus）或灰烬（Ash），它会停止掉落并生成一个新的生命值（rb）。
+    // 灰烬是肥料：落在灰烬上的种子 ra 增加 ASH_FERTILIZER，茎能长得更高。
     // 如果种子落在空白位置，种子会继续掉落到下方。
     // 如果种子下方是水（Water）、气体（Gas）、油（Oil）或酸（Acid），种子会与之互动并继续下落。
     if rb == 0 {
@@ -1235,9 +1577,15 @@ pub fn update_seed(cell: Cell, mut api: SandApi) {
         if nbr_species_below == Species::Sand
             || nbr_species_below == Species::Plant
             || nbr_species_below == Species::Fungus
+            || nbr_species_below == Species::Ash
         {
             let rb = (api.rand_int(253) + 1) as u8;
-            api.set(0, 0, Cell { rb, ..cell });
+            let ra = if nbr_species_below == Species::Ash {
+                ra.saturating_add(ASH_FERTILIZER)
+            } else {
+                ra
+            };
+            api.set(0, 0, Cell { ra, rb, ..cell });
             return;
         }
 
@@ -1309,7 +1657,7 @@ pub fn update_seed(cell: Cell, mut api: SandApi) {
                     && (api.get(ldx, ldy).species == Species::Empty
                         || api.get(rdx, rdy).species == Species::Empty)
                 {
-                    let i = generate_random() as i32;
+                    let i = generate_random(&mut api) as i32;
                     let dec = 9 - api.rand_int(3);
                     if (i + ra as i32) > 100 {
                         api.set(
@@ -1328,7 +1676,8 @@ pub fn update_seed(cell: Cell, mut api: SandApi) {
                 //
                 // 如果种子附近是水（Water），种子会转变为新的一颗种子（Species::Seed）。
                 if nbr_species == Species::Water {
-                    api.set(dx, dy, Cell::new(Species::Seed))
+                    let seed = api.new_cell(Species::Seed);
+                    api.set(dx, dy, seed)
                 }
             }
         }
@@ -1350,9 +1699,9 @@ pub fn update_fungus(cell: Cell, mut api: SandApi) {
 
     // 2 火焰与岩浆扩散：
     //
-    // 如果当前 rb == 0 且相邻的单元格是火焰（Fire）或岩浆（Lava），则创建一个新的真菌并将其放置在当前位置。
+    // 如果当前 rb == 0 且所在格子的温度达到着火点（与植物相同），则创建一个新的真菌并将其放置在当前位置。
     // api.set(0, 0, Cell {...})：将当前位置的细胞替换为新的真菌细胞。
-    if rb == 0 && nbr_species == Species::Fire || nbr_species == Species::Lava {
+    if rb == 0 && api.get_temp() >= PLANT_IGNITE_TEMP {
         api.set(
             0,
             0,
@@ -1480,7 +1829,7 @@ pub fn update_fungus(cell: Cell, mut api: SandApi) {
             && api.get(ldx, ldy).species != Species::Fungus
             && api.get(rdx, rdy).species != Species::Fungus
         {
-            let i =generate_random() as i32;
+            let i =generate_random(&mut api) as i32;
             let dec = 15 - api.rand_int(20);
             if (i + ra as i32) > 165 {
                 api.set(
@@ -1511,7 +1860,7 @@ pub fn update_acid(cell: Cell, mut api: SandApi) {
     // 如果酸的腐蚀程度小于 80（degraded.ra < 80），则酸会消失（设置为空单元格 EMPTY_CELL）。
     let ra = cell.ra;
     let mut degraded = cell.clone();
-    degraded.ra = ra - 60;
+    degraded.ra = ra.wrapping_sub(60);
     // i = api.rand_int(100);
     if degraded.ra < 80 {
         degraded = EMPTY_CELL;
@@ -1537,22 +1886,20 @@ pub fn update_acid(cell: Cell, mut api: SandApi) {
         api.set(-dx, 0, cell);
     } else {
         // 向上
-        if api.get(0, 1).species != Species::Wall && api.get(0, 1).species != Species::Acid {
+        let below = api.get(0, 1).species;
+        let side = api.get(dx, 0).species;
+        let other_side = api.get(-dx, 0).species;
+        let above = api.get(0, -1).species;
+        if !acid_blocked(&mut api, below) {
             api.set(0, 0, EMPTY_CELL);
             api.set(0, 1, degraded);
-        } else if api.get(dx, 0).species != Species::Wall && api.get(dx, 0).species != Species::Acid
-        {
+        } else if !acid_blocked(&mut api, side) {
             api.set(0, 0, EMPTY_CELL);
             api.set(dx, 0, degraded);
-        } else if api.get(-dx, 0).species != Species::Wall
-            && api.get(-dx, 0).species != Species::Acid
-        {
+        } else if !acid_blocked(&mut api, other_side) {
             api.set(0, 0, EMPTY_CELL);
             api.set(-dx, 0, degraded);
-        } else if api.get(0, -1).species != Species::Wall
-            && api.get(0, -1).species != Species::Acid
-            && api.get(0, -1).species != Species::Empty
-        {
+        } else if above != Species::Empty && !acid_blocked(&mut api, above) {
             // 4 酸的腐蚀行为：
             //
             // 如果酸能够移动到空单元格，它会将自己放到新位置，并将当前单元格清空。
@@ -1568,6 +1915,66 @@ pub fn update_acid(cell: Cell, mut api: SandApi) {
     }
 }
 
+// 酸腐蚀不了墙和酸；自定义材质 X 按配置的概率抵抗腐蚀
+fn acid_blocked(api: &mut SandApi, species: Species) -> bool {
+    species == Species::Wall
+        || species == Species::Acid
+        || (species == Species::X
+            && api.rand_float() < api.universe.registry.custom_material().corrosion_resistance)
+}
+
+// 自定义材质 X（参数见 custom_material.rs）：
+// 温度达到 CUSTOM_IGNITE_TEMP 时按 flammability 的概率着火；gravity 决定下落还是上浮，先直走再斜走，
+// 都走不了时按 liquidity 的概率向两侧流动；比密度更小（上浮时是更大）的液体会被它挤开。
+pub fn update_x(cell: Cell, mut api: SandApi) {
+    let material = api.universe.registry.custom_material();
+    let gravity = material.gravity;
+    let liquidity = material.liquidity;
+    let flammability = material.flammability;
+    let density = material.density;
+
+    if api.get_temp() >= CUSTOM_IGNITE_TEMP && api.rand_float() < flammability {
+        api.set(
+            0,
+            0,
+            Cell {
+                species: Species::Fire,
+                ra: (150 + (cell.ra / 10)) as u8,
+                rb: 0,
+                clock: 0,
+            },
+        );
+        return;
+    }
+    if gravity == 0 {
+        return;
+    }
+
+    let dx = api.rand_dir_2();
+    let nbr = api.get(0, gravity);
+    if nbr.species == Species::Empty {
+        api.set(0, 0, EMPTY_CELL);
+        api.set(0, gravity, cell);
+    } else if api.get(dx, gravity).species == Species::Empty {
+        api.set(0, 0, EMPTY_CELL);
+        api.set(dx, gravity, cell);
+    } else if liquidity > 0.0 && api.get(dx, 0).species == Species::Empty && api.rand_float() < liquidity {
+        api.set(0, 0, EMPTY_CELL);
+        api.set(dx, 0, cell);
+    } else if nbr.species == Species::Water
+        || nbr.species == Species::Gas
+        || nbr.species == Species::Oil
+        || nbr.species == Species::Acid
+    {
+        let nbr_density = api.universe.registry.density(nbr.species);
+        let displaces = if gravity > 0 { nbr_density < density } else { nbr_density > density };
+        if displaces {
+            api.set(0, 0, nbr);
+            api.set(0, gravity, cell);
+        }
+    }
+}
+
 pub fn update_mite(cell: Cell, mut api: SandApi) {
     // 1：初始设置：
     // 生成一个随机整数，dx 和 dy 代表螨虫的移动方向。
@@ -1677,12 +2084,10 @@ pub fn update_mite(cell: Cell, mut api: SandApi) {
     }
 }
 
-fn generate_random() -> f64 {
-    let mut rng = rand::thread_rng();
-
-    // 生成两个 0 到 1 之间的随机浮点数
-    let random1: f64 = rng.gen(); // 第一个随机数
-    let random2: f64 = rng.gen(); // 第二个随机数
+fn generate_random(api: &mut SandApi) -> f64 {
+    // 生成两个 0 到 1 之间的随机浮点数（来自宇宙的 rng）
+    let random1: f64 = api.rand_float(); // 第一个随机数
+    let random2: f64 = api.rand_float(); // 第二个随机数
 
     // 返回两个随机数相乘并乘以 100
     random1 * random2 * 100.0
@@ -0,0 +1,190 @@
+use std::fs;
+use std::path::Path;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use crate::brush::{world_to_cell, Brush};
+use crate::cli::CliArgs;
+use crate::convert_svg::{import_svg, rgba_to_species, ColorMapping, SvgImport};
+use crate::replay::not_replaying;
+use crate::species::Species;
+use crate::universe::Universe;
+use crate::world_config::WorldConfig;
+use crate::world_file::{load_world_on_startup, WorldFileError};
+
+// 图章：把任意 PNG/JPEG 图片通过 rgba_to_species 逐像素转换成细胞，方便在图片编辑器里画关卡。
+// 透明像素保持原样，白色像素会擦除（Empty），很浅的蓝色和青色分别变成雪和蒸汽（见 rgba_to_species）。
+
+// 图章放到宇宙中的方式
+#[derive(Clone, Copy, Debug)]
+pub enum StampFit {
+    // 拉伸铺满整个宇宙
+    Universe,
+    // 缩放到指定矩形，左上角在 (x, y)
+    Rect { x: i32, y: i32, width: u32, height: u32 },
+}
+
+pub fn load_stamp_image(path: &Path) -> Result<RgbaImage, WorldFileError> {
+    Ok(image::open(path)?.to_rgba8())
+}
+
+pub fn stamp_image(universe: &mut Universe, image: &RgbaImage, fit: StampFit) {
+    let (x0, y0, width, height) = match fit {
+        StampFit::Universe => (0, 0, universe.width as u32, universe.height as u32),
+        StampFit::Rect { x, y, width, height } => (x, y, width, height),
+    };
+    if width == 0 || height == 0 {
+        return;
+    }
+    // 最近邻缩放，避免插值出原图里没有的颜色
+    let scaled;
+    let image = if (width, height) == image.dimensions() {
+        image
+    } else {
+        scaled = imageops::resize(image, width, height, FilterType::Nearest);
+        &scaled
+    };
+
+    let registry = universe.registry.clone();
+    for (px, py, pixel) in image.enumerate_pixels() {
+        let [r, g, b, a] = pixel.0;
+        if a < 250 {
+            continue;
+        }
+        let species = Species::from_id(rgba_to_species(&registry, r, g, b, a));
+        universe.place(x0 + px as i32, y0 + py as i32, species);
+    }
+}
+
+// 图章插件：--stamp <path> 在启动时把图片铺满宇宙，--svg <path> 导入矢量关卡；
+// 运行时按 T 以光标为中心、按画笔大小缩放盖章，Shift+T 铺满整个宇宙。
+pub struct StampPlugin;
+
+#[derive(Resource, Default)]
+pub struct StampImage(pub Option<RgbaImage>);
+
+// 画笔大小到图章矩形宽度的倍数
+const STAMP_BRUSH_SCALE: u32 = 4;
+
+// --svg-legend 时填充色与图例颜色的最大距离（RGB 欧氏距离）
+const SVG_LEGEND_TOLERANCE: u32 = 48;
+
+impl Plugin for StampPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<StampImage>()
+            .add_systems(Startup, (
+                load_stamp_on_startup.after(load_world_on_startup),
+                load_svg_on_startup.after(load_stamp_on_startup),
+            ))
+            .add_systems(Update, stamp_input_system.run_if(not_replaying));
+    }
+}
+
+fn load_stamp_on_startup(
+    args: Res<CliArgs>,
+    mut stamp: ResMut<StampImage>,
+    mut universe: ResMut<Universe>,
+) {
+    stamp.0 = stamp_from_args(&mut universe, &args);
+}
+
+pub fn load_svg_on_startup(args: Res<CliArgs>, mut universe: ResMut<Universe>) {
+    import_svg_from_args(&mut universe, &args);
+}
+
+// 处理 --stamp：把图片铺满宇宙，返回图片以便之后按 T 盖章
+pub fn stamp_from_args(universe: &mut Universe, args: &CliArgs) -> Option<RgbaImage> {
+    let path = args.stamp.as_ref()?;
+    match load_stamp_image(path) {
+        Ok(image) => {
+            stamp_image(universe, &image, StampFit::Universe);
+            println!("stamped {}", path.display());
+            Some(image)
+        }
+        Err(err) => {
+            eprintln!("failed to load stamp {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+// 处理 --svg / --svg-rect / --svg-legend
+pub fn import_svg_from_args(universe: &mut Universe, args: &CliArgs) {
+    let Some(path) = args.svg.as_ref() else {
+        return;
+    };
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("failed to read svg {}: {}", path.display(), err);
+            return;
+        }
+    };
+    let mut import = SvgImport::fit_universe(universe);
+    if let Some((x, y, width, height)) = args.svg_rect {
+        import.offset_x = x;
+        import.offset_y = y;
+        import.width = width;
+        import.height = height;
+    }
+    // 图例在注册表（包括 --material）准备好之后才解析，物种名才能查到
+    if let Some(legend) = args.svg_legend.as_ref() {
+        match ColorMapping::parse_legend(legend, &universe.registry, SVG_LEGEND_TOLERANCE) {
+            Some(mapping) => import.mapping = mapping,
+            None => eprintln!("--svg-legend expects registry or rrggbb=<species>,..."),
+        }
+    }
+    match import_svg(universe, &data, &import) {
+        Ok(()) => println!("imported svg {}", path.display()),
+        Err(err) => eprintln!("failed to import svg {}: {}", path.display(), err),
+    }
+}
+
+fn stamp_input_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    brush: Res<Brush>,
+    config: Res<WorldConfig>,
+    stamp: Res<StampImage>,
+    mut universe: ResMut<Universe>,
+) {
+    if !keys.just_pressed(KeyCode::KeyT) {
+        return;
+    }
+    let Some(image) = stamp.0.as_ref() else {
+        return;
+    };
+
+    if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        universe.push_undo();
+        stamp_image(&mut universe, image, StampFit::Universe);
+        return;
+    }
+
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+    let Some(world_pos) = window
+        .cursor_position()
+        .and_then(|pos| camera.viewport_to_world_2d(camera_transform, pos)) else {
+        return;
+    };
+    let (cx, cy) = world_to_cell(&universe, config.cell_scale, world_pos);
+
+    // 保持图片宽高比
+    let width = brush.size.max(1) as u32 * STAMP_BRUSH_SCALE;
+    let height = (width as u64 * image.height() as u64 / image.width().max(1) as u64).max(1) as u32;
+    universe.push_undo();
+    stamp_image(&mut universe, image, StampFit::Rect {
+        x: cx - width as i32 / 2,
+        y: cy - height as i32 / 2,
+        width,
+        height,
+    });
+}
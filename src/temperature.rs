@@ -0,0 +1,77 @@
+use crate::chunks::CHUNK_SIZE;
+use crate::universe::Universe;
+
+// 温度场：每个细胞一个温度（单位大致是摄氏度），与 winds / burns 并列存放在 Universe 里。
+// 每个 tick 的细胞更新之后扩散一次：
+//   1. 每个细胞向上下左右四个邻居的平均值靠拢一半（越界的邻居按自身温度算，边缘是绝热的）
+//   2. 所有细胞每 tick 向室温靠拢 1 度
+//   3. 注册表里有 fixed_temp 的物种把所在格子的温度固定住：火、岩浆是热源，冰是冷源
+// 物种规则通过 SandApi::get_temp / set_temp 读写自己格子的温度，按下面的阈值着火、融化。
+// 扩散只处理本 tick 处理的块（醒着的块及其邻居）。温度与室温相差超过 1 度的块会被唤醒，
+// 所以热量会一圈一圈传进休眠的区域（石墙后面的木头也会被烤着），块也不会带着余温进入休眠（所以有热源或冷源的块一直醒着）。
+// reset 和撤销/重做只恢复细胞，温度场一并回到室温。
+
+pub const AMBIENT_TEMP: i16 = 20;
+
+// 冰和雪在室温下不会融化（原版的行为），靠近热源时才融化
+pub const ICE_MELT_TEMP: i16 = 50;
+pub const SNOW_MELT_TEMP: i16 = 35;
+// 冰的 fixed_temp；水冷到冰点以下会结冰，所以冰会慢慢冻住挨着它的水
+pub const ICE_TEMP: i16 = -40;
+pub const WATER_FREEZE_TEMP: i16 = 0;
+pub const WOOD_IGNITE_TEMP: i16 = 250;
+pub const PLANT_IGNITE_TEMP: i16 = 200;
+pub const SEED_IGNITE_TEMP: i16 = 150;
+// 水达到沸点变成蒸汽，蒸汽在低于沸点的格子里慢慢冷凝
+pub const WATER_BOIL_TEMP: i16 = 100;
+pub const GUNPOWDER_IGNITE_TEMP: i16 = 150;
+// 自定义材质 X 达到这个温度后按 flammability 的概率着火
+pub const CUSTOM_IGNITE_TEMP: i16 = 200;
+
+impl Universe {
+    pub(crate) fn reset_temps(&mut self) {
+        self.temps.fill(AMBIENT_TEMP);
+    }
+
+    pub(crate) fn diffuse_temps(&mut self) {
+        // 源先把自己的格子固定住，邻居按固定值扩散；源自己存下的是扩散后的值，
+        // 所以物种规则读到的是受周围影响后的温度（冰挨着火会融化）
+        let mut old = self.temps.clone();
+        for (t, cell) in old.iter_mut().zip(self.cells.iter()) {
+            if let Some(fixed) = self.registry.fixed_temp(cell.species) {
+                *t = fixed;
+            }
+        }
+        let width = self.width;
+        let height = self.height;
+        for cx in 0..self.chunks.cols() {
+            for cy in 0..self.chunks.rows() {
+                if !self.chunks.is_active(cx, cy) {
+                    continue;
+                }
+                let mut warm = false;
+                for x in cx * CHUNK_SIZE..((cx + 1) * CHUNK_SIZE).min(width) {
+                    for y in cy * CHUNK_SIZE..((cy + 1) * CHUNK_SIZE).min(height) {
+                        let i = self.get_index(x, y);
+                        let t = old[i] as i32;
+                        let at = |nx: i32, ny: i32| {
+                            if nx < 0 || nx >= width || ny < 0 || ny >= height {
+                                t
+                            } else {
+                                old[(nx * height + ny) as usize] as i32
+                            }
+                        };
+                        let sum = at(x - 1, y) + at(x + 1, y) + at(x, y - 1) + at(x, y + 1);
+                        let mut next = t + (sum - 4 * t) / 8;
+                        next += (AMBIENT_TEMP as i32 - next).signum();
+                        self.temps[i] = next.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+                        warm |= (next - AMBIENT_TEMP as i32).abs() > 1;
+                    }
+                }
+                if warm {
+                    self.chunks.wake(cx * CHUNK_SIZE, cy * CHUNK_SIZE);
+                }
+            }
+        }
+    }
+}
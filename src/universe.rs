@@ -1,12 +1,25 @@
 
-use rand::{Rng, SeedableRng};
+use rand::{Rng, RngCore, SeedableRng};
 use rand_xoshiro::SplitMix64;
 
 use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::Arc;
 use bevy::a11y::accesskit::Role::Math;
 use bevy::prelude::Resource;
 use bytemuck::{Pod,Zeroable};
+use crate::blast::Blast;
+use crate::boundary::{Boundaries, Resolved};
+use crate::chunks::{ChunkMap, CHUNK_SIZE};
+use crate::emitter::{EmitterArea, Emitter};
+use crate::parallel::TickSchedule;
+use crate::registry::SpeciesRegistry;
+use crate::replay::{Recorder, ReplayEvent};
+use crate::rle::Rle;
 use crate::species::Species;
+use crate::temperature::AMBIENT_TEMP;
+use crate::world_config::{WorldConfig, DEFAULT_WORLD_HEIGHT, DEFAULT_WORLD_WIDTH};
+use crate::world_file::encode_world;
 // use web_sys::console;
 // 风（Wind）和细胞（Cell）的数据结构以及 Universe（宇宙）的一部分实现
 // Wind 结构体表示风的特性，其中：
@@ -23,6 +36,14 @@ pub struct Wind {
     pub(crate) density: u8,
 }
 
+// 无风时 winds 的值：blow_wind 以 126 为零点，dx 和 dy 都是 126 时不推动任何细胞
+pub const CALM_WIND: Wind = Wind {
+    dx: 126,
+    dy: 126,
+    pressure: 0,
+    density: 0,
+};
+
 // Cell 代表了模拟中的一个单元，包含以下字段：
 //
 // species：细胞的物种类型（例如沙子、墙壁、植物等）。这些物种应该是通过一个 Species 枚举类型来表示的。
@@ -40,7 +61,7 @@ pub struct Cell {
 }
 
 #[repr(C)]
-#[derive(Pod, Zeroable,Copy,Clone)]
+#[derive(Pod, Zeroable,Copy,Clone, Debug, PartialEq, Eq)]
 pub struct CellData {
     pub(crate) species: u8,
     pub(crate) ra: u8,
@@ -48,15 +69,28 @@ pub struct CellData {
     pub(crate) clock: u8,
 }
 
+// 从 GPU 回读或文件加载的字节转换回 Cell，未知物种 id 解码为 Species::Unknown
+impl From<CellData> for Cell {
+    fn from(data: CellData) -> Self {
+        Cell {
+            species: Species::from_id(data.species),
+            ra: data.ra,
+            rb: data.rb,
+            clock: data.clock,
+        }
+    }
+}
+
 
 // Cell 的方法：
 // new：这是一个构造函数，创建一个新的 Cell 实例。它会基于物种（species）和随机生成的数值来初始化 ra 和 rb 属性。
-// update：调用细胞的 species 更新方法来改变细胞的状态。这个方法通过 SandApi（API 代理）来执行物种的更新逻辑。
+//      随机数必须来自宇宙的 rng（见 SandApi::new_cell），这样同一个种子的运行结果才能复现。
+// update：从物种注册表中查出该物种的更新函数并调用。这个方法通过 SandApi（API 代理）来执行物种的更新逻辑。
 impl Cell {
-    pub fn new(species: Species) -> Cell {
+    pub fn new<R: Rng>(species: Species, rng: &mut R) -> Cell {
         Cell {
             species: species,
-            ra:  rand::thread_rng().gen_range(0..150) as u8,
+            ra: rng.gen_range(0..150) as u8,
             rb: 0,
             clock: 0,
         }
@@ -71,7 +105,9 @@ impl Cell {
         }
     }
     pub fn update(&self, api: SandApi) {
-        self.species.update(*self, api);
+        if let Some(update) = api.universe.registry.update_fn(self.species) {
+            update(*self, api);
+        }
     }
 }
 
@@ -87,37 +123,79 @@ pub static EMPTY_CELL: Cell = Cell {
 // width 和 height：宇宙的尺寸（宽度和高度），决定了细胞的排列方式。
 // cells：一个 Vec<Cell>，用于存储宇宙中的所有细胞。
 // undo_stack：用于撤销操作的栈，保存了历史状态。这允许在模拟过程中回退到之前的状态。
+// redo_stack：被撤销的状态，任何新的编辑（push_undo）都会清空它。两个栈都保存 RLE 压缩后的快照。
 // winds 和 burns：分别表示宇宙中每个位置的风数据和烧伤状态。它们是与 Wind 类型相关的向量。
 // generation：宇宙当前的代数，通常用于追踪模拟的进度。
-// rng：SplitMix64 是一个伪随机数生成器，用于生成模拟中的随机事件。
+// rng：SplitMix64 是一个伪随机数生成器，用于生成模拟中的随机事件。模拟里所有的随机数都必须从这里取。
+// seed：rng 的种子，同一个种子加上同样的输入可以逐位复现一次运行。
+// dirty：自上次上传到 GPU 以来被修改过的列范围（x 的闭区间），用于只上传变化的部分。
+// registry：物种注册表，决定每个物种的更新规则和风阈值。
+// recorder：录制中的操作录像（见 replay.rs），为 None 时不录制。
+// chunks：分块休眠状态（见 chunks.rs），tick 跳过休眠的区域。
+// schedule：tick 的调度方式，串行扫描或棋盘式分块并行（见 parallel.rs）。
+// deterministic：确定性模式，串行调度也按棋盘式分块的顺序执行，结果与并行调度逐位相同（见 parallel.rs）。
+// regions：棋盘式分块复用的区域缓冲，每个块每个阶段装入一次，不再每次新建。
+// touched：只在分块的区域里使用，记录本阶段被 SandApi 写过的格子，写回时只处理这些格子；整个宇宙上为 None。
+// boundaries：四条边的边界规则（墙、虚空或环绕，见 boundary.rs）。
+// emitters：发射器和排水口（见 emitter.rs），每个 tick 开始时处理。
+// temps：每个细胞的温度，每个 tick 扩散一次（见 temperature.rs）。
+
+// 默认种子（沿用原来写死的常量）
+pub const DEFAULT_SEED: u64 = 0x734f6b89de5f83cc;
+
+// 撤销栈最多保存的快照数
+const UNDO_LIMIT: usize = 50;
 
 #[derive(Resource)]
 pub struct Universe {
     pub(crate) width: i32,
     pub(crate) height: i32,
     pub(crate) cells: Vec<Cell>,
-    pub(crate) undo_stack: VecDeque<Vec<Cell>>,
+    pub(crate) undo_stack: VecDeque<Rle<Cell>>,
+    pub(crate) redo_stack: Vec<Rle<Cell>>,
     pub(crate) winds: Vec<Wind>,
     pub(crate) burns: Vec<Wind>,
+    pub(crate) temps: Vec<i16>,
     pub(crate) generation: u8,
     pub(crate) rng: SplitMix64,
+    pub(crate) seed: u64,
+    pub(crate) dirty: Option<(i32, i32)>,
+    pub(crate) registry: Arc<SpeciesRegistry>,
+    pub(crate) recorder: Option<Recorder>,
+    pub(crate) chunks: ChunkMap,
+    pub(crate) schedule: TickSchedule,
+    pub(crate) deterministic: bool,
+    pub(crate) regions: Vec<Universe>,
+    pub(crate) touched: Option<Vec<usize>>,
+    pub(crate) boundaries: Boundaries,
+    pub(crate) emitters: Vec<Emitter>,
+    // 本 tick 请求的爆炸，tick 结束前处理完（见 blast.rs）
+    pub(crate) blasts: Vec<Blast>,
 }
 impl Default for Universe {
     fn default() -> Self {
-        let n = 300 as i32;
-        let h = (n / 2 )as i32;
+        Universe::demo_scene(DEFAULT_WORLD_WIDTH as i32, DEFAULT_WORLD_HEIGHT as i32, DEFAULT_SEED)
+    }
+}
+
+impl Universe {
+    // 启动时的演示场景，绘制用的随机数来自给定种子
+    pub fn demo_scene(width: i32, height: i32, seed: u64) -> Self {
+        let n = width.min(height);
+        let cx = width / 2;
+        let cy = height / 2;
         let d = (n as f64 * 0.9) as i32;
-        let mut universe = Universe::new(n , n );
+        let mut universe = Universe::with_seed(width, height, seed);
         universe.paint(10, 10, 10, Species::Sand);
 
-        universe.paint(h, h, d + 2, Species::Plant);
-        universe.paint(30, n - 10, 15, Species::Fire);
-        universe.paint(h - 30, n - 10, 15, Species::Fire);
+        universe.paint(cx, cy, d + 2, Species::Plant);
+        universe.paint(30, height - 10, 15, Species::Fire);
+        universe.paint(cx - 30, height - 10, 15, Species::Fire);
         // universe.paint(h, h, n / 3, Species::Air);
         // 空气
-        universe.paint(h, h, n / 3, Species::Empty);
+        universe.paint(cx, cy, n / 3, Species::Empty);
 
-        universe.paint(h, h, n / 3, Species::Fire);
+        universe.paint(cx, cy, n / 3, Species::Fire);
         universe
     }
 }
@@ -134,30 +212,41 @@ impl<'a> SandApi<'a> {
         }
         let nx = self.x + dx;
         let ny = self.y + dy;
-        if nx < 0 || nx > self.universe.width - 1 || ny < 0 || ny > self.universe.height - 1 {
-            return Cell {
+        let universe = &self.universe;
+        match universe.boundaries.resolve(nx, ny, universe.width, universe.height) {
+            Resolved::Inside(nx, ny) => universe.get_cell(nx, ny),
+            Resolved::Wall => Cell {
                 species: Species::Wall,
                 ra: 0,
                 rb: 0,
-                clock: self.universe.generation,
-            };
+                clock: universe.generation,
+            },
+            Resolved::Void => Cell {
+                clock: universe.generation,
+                ..EMPTY_CELL
+            },
         }
-        self.universe.get_cell(nx, ny)
     }
     pub fn set(&mut self, dx: i32, dy: i32, v: Cell) {
         if dx > 2 || dx < -2 || dy > 2 || dy < -2 {
             panic!("oob set");
         }
-        let nx = self.x + dx;
-        let ny = self.y + dy;
-
-        if nx < 0 || nx > self.universe.width - 1 || ny < 0 || ny > self.universe.height - 1 {
+        let universe = &self.universe;
+        // 越界写入：Wall 和 Void 都丢弃（Void 下离开地图的细胞就此消失），Wrap 写到对边
+        let Resolved::Inside(nx, ny) =
+            universe.boundaries.resolve(self.x + dx, self.y + dy, universe.width, universe.height) else {
             return;
-        }
+        };
         let i = self.universe.get_index(nx, ny);
+        let old = self.universe.cells[i];
+        if old.species != v.species || old.ra != v.ra || old.rb != v.rb {
+            self.universe.chunks.wake(nx, ny);
+        }
         // v.clock += 1;
         self.universe.cells[i] = v;
         self.universe.cells[i].clock = self.universe.generation.wrapping_add(1);
+        self.universe.mark_dirty(nx);
+        self.universe.touch(i);
     }
     pub fn get_fluid(&mut self) -> Wind {
         let idx = self.universe.get_index(self.x, self.y);
@@ -168,12 +257,44 @@ impl<'a> SandApi<'a> {
         let idx = self.universe.get_index(self.x, self.y);
 
         self.universe.burns[idx] = v;
+        self.universe.touch(idx);
+    }
+
+    // 当前格子的温度
+    pub fn get_temp(&mut self) -> i16 {
+        let idx = self.universe.get_index(self.x, self.y);
+        self.universe.temps[idx]
+    }
+
+    pub fn set_temp(&mut self, t: i16) {
+        let idx = self.universe.get_index(self.x, self.y);
+        self.universe.temps[idx] = t;
+        self.universe.touch(idx);
+    }
+
+    // 请求一次以当前格子为圆心的爆炸，本 tick 的细胞更新结束后才炸开，半径不受 ±2 的限制
+    pub fn explode(&mut self, radius: i32) {
+        self.universe.blasts.push(Blast {
+            x: self.x,
+            y: self.y,
+            radius,
+        });
     }
 
     pub fn rand_int(&mut self, n: i32) -> i32 {
         self.universe.rng.gen_range(0..n)
     }
 
+    // [0, 1) 之间的随机浮点数
+    pub fn rand_float(&mut self) -> f64 {
+        self.universe.rng.gen()
+    }
+
+    // 用宇宙的 rng 创建新细胞
+    pub fn new_cell(&mut self, species: Species) -> Cell {
+        Cell::new(species, &mut self.universe.rng)
+    }
+
     pub fn once_in(&mut self, n: i32) -> bool {
         self.rand_int(n) == 0
     }
@@ -223,32 +344,61 @@ impl<'a> SandApi<'a> {
 
 impl Universe {
     pub fn reset(&mut self) {
+        self.record(ReplayEvent::Reset);
         for x in 0..self.width {
             for y in 0..self.height {
                 let idx = self.get_index(x, y);
                 self.cells[idx] = EMPTY_CELL;
             }
         }
+        self.winds.fill(CALM_WIND);
+        self.reset_temps();
+        self.mark_all_dirty();
+        self.chunks.wake_all();
     }
     pub fn tick(&mut self) {
+        self.apply_emitters();
+        match self.schedule {
+            // 分块的区域不会绕到对边，有 Wrap 边界时两种调度都退回串行扫描
+            _ if self.boundaries.wraps() => self.tick_serial(),
+            // 确定性模式下串行调度在当前线程上按棋盘式分块执行，结果与多线程逐位相同
+            TickSchedule::Serial if self.deterministic => self.tick_checkerboard(1),
+            TickSchedule::Serial => self.tick_serial(),
+            TickSchedule::Checkerboard { threads } => self.tick_checkerboard(threads),
+        }
+        self.apply_blasts();
+        self.diffuse_temps();
+        if self.recorder.is_some() {
+            let checksum = self.checksum();
+            self.record(ReplayEvent::Tick { checksum });
+        }
+    }
+
+    fn tick_serial(&mut self) {
         // let mut next = self.cells.clone();
         // let dx = self.winds[(self.width * self.height / 2) as usize].dx;
         // let js: JsValue = (dx).into();
         // console::log_2(&"dx: ".into(), &js);
 
+        self.chunks.begin_tick(&self.boundaries);
         for x in 0..self.width {
-            for y in 0..self.height {
-                let cell = self.get_cell(x, y);
-                let wind = self.get_wind(x, y);
-                Universe::blow_wind(
-                    cell,
-                    wind,
-                    SandApi {
-                        universe: self,
-                        x,
-                        y,
-                    },
-                )
+            for cy in 0..self.chunks.rows() {
+                if !self.chunks.is_active(x / CHUNK_SIZE, cy) {
+                    continue;
+                }
+                for y in self.chunk_rows(cy) {
+                    let cell = self.get_cell(x, y);
+                    let wind = self.get_wind(x, y);
+                    Universe::blow_wind(
+                        cell,
+                        wind,
+                        SandApi {
+                            universe: self,
+                            x,
+                            y,
+                        },
+                    )
+                }
             }
         }
         self.generation = self.generation.wrapping_add(1);
@@ -259,27 +409,37 @@ impl Universe {
                 x
             };
 
-            for y in 0..self.height {
-                let idx = self.get_index(scanx, y);
-                let cell = self.get_cell(scanx, y);
-
-                self.burns[idx] = Wind {
-                    dx: 0,
-                    dy: 0,
-                    pressure: 0,
-                    density: 0,
-                };
-                Universe::update_cell(
-                    cell,
-                    SandApi {
-                        universe: self,
-                        x: scanx,
-                        y,
-                    },
-                );
+            for cy in 0..self.chunks.rows() {
+                if !self.chunks.is_active(scanx / CHUNK_SIZE, cy) {
+                    continue;
+                }
+                for y in self.chunk_rows(cy) {
+                    let idx = self.get_index(scanx, y);
+                    let cell = self.get_cell(scanx, y);
+
+                    self.burns[idx] = Wind {
+                        dx: 0,
+                        dy: 0,
+                        pressure: 0,
+                        density: 0,
+                    };
+                    // 会随机变化的物种让所在的块保持清醒
+                    if self.registry.always_active(cell.species) {
+                        self.chunks.wake(scanx, y);
+                    }
+                    Universe::update_cell(
+                        cell,
+                        SandApi {
+                            universe: self,
+                            x: scanx,
+                            y,
+                        },
+                    );
+                }
             }
         }
 
+        self.chunks.end_tick();
         self.generation = self.generation.wrapping_add(1);
     }
 
@@ -303,6 +463,11 @@ impl Universe {
         self.burns.as_ptr()
     }
     pub fn paint(&mut self, x: i32, y: i32, size: i32, species: Species) {
+        self.record(if species == Species::Empty {
+            ReplayEvent::Erase { x, y, size }
+        } else {
+            ReplayEvent::Paint { x, y, size, species }
+        });
         let size = size;
         let radius: f64 = (size as f64) / 2.0;
 
@@ -330,39 +495,90 @@ impl Universe {
                             + ((self.generation % 127) as i8 - 60).abs() as u8,
                         rb: 0,
                         clock: self.generation,
-                    }
+                    };
+                    self.mark_dirty(px);
+                    self.chunks.wake(px, py);
                 }
             }
         }
     }
 
+    // 在单个格子放置一个新细胞（越界则忽略），ra 的取法与 paint 一致
+    pub fn place(&mut self, x: i32, y: i32, species: Species) {
+        if x < 0 || x > self.width - 1 || y < 0 || y > self.height - 1 {
+            return;
+        }
+        self.record(ReplayEvent::Place { x, y, species });
+        let i = self.get_index(x, y);
+        self.cells[i] = Cell {
+            species,
+            ra: 60
+                + (self.rng.gen::<f32>() * 30.) as u8
+                + ((self.generation % 127) as i8 - 60).abs() as u8,
+            rb: 0,
+            clock: self.generation,
+        };
+        self.mark_dirty(x);
+        self.chunks.wake(x, y);
+    }
+
     pub fn push_undo(&mut self) {
-        self.undo_stack.push_front(self.cells.clone());
-        self.undo_stack.truncate(50);
+        self.record(ReplayEvent::PushUndo);
+        self.undo_stack.push_front(Rle::encode(&self.cells));
+        self.undo_stack.truncate(UNDO_LIMIT);
+        self.redo_stack.clear();
     }
 
     pub fn pop_undo(&mut self) {
-        let old_state = self.undo_stack.pop_front();
-        match old_state {
-            Some(state) => self.cells = state,
-            None => (),
+        self.record(ReplayEvent::Undo);
+        let Some(state) = self.undo_stack.pop_front() else {
+            return;
         };
+        // 快照恢复成功之后才把当前状态放进重做栈，尺寸不符的快照直接丢掉
+        let current = Rle::encode(&self.cells);
+        if self.restore_snapshot(&state) {
+            self.redo_stack.push(current);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        self.record(ReplayEvent::Redo);
+        let Some(state) = self.redo_stack.pop() else {
+            return;
+        };
+        let current = Rle::encode(&self.cells);
+        if self.restore_snapshot(&state) {
+            self.undo_stack.push_front(current);
+            self.undo_stack.truncate(UNDO_LIMIT);
+        }
     }
 
     pub fn flush_undos(&mut self) {
         self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    // 快照与当前宇宙大小不符时不恢复，返回 false
+    fn restore_snapshot(&mut self, state: &Rle<Cell>) -> bool {
+        if state.len() != self.cells.len() {
+            return false;
+        }
+        state.decode_into(&mut self.cells);
+        // 快照只保存细胞，温度回到室温，免得旧的热量留在恢复出来的格子上
+        self.reset_temps();
+        self.mark_all_dirty();
+        self.chunks.wake_all();
+        true
     }
 
     pub fn new(width: i32, height: i32) -> Universe {
+        Universe::with_seed(width, height, DEFAULT_SEED)
+    }
+
+    pub fn with_seed(width: i32, height: i32, seed: u64) -> Universe {
         let cells = (0..width * height).map(|_i| EMPTY_CELL).collect();
-        let winds: Vec<Wind> = (0..width * height)
-            .map(|_i| Wind {
-                dx: 0,
-                dy: 0,
-                pressure: 0,
-                density: 0,
-            })
-            .collect();
+        // 目前没有流体阶段写入 winds，保持无风，否则所有细胞都会被吹向左上方
+        let winds: Vec<Wind> = (0..width * height).map(|_i| CALM_WIND).collect();
 
         let burns: Vec<Wind> = (0..width * height)
             .map(|_i| Wind {
@@ -372,38 +588,249 @@ impl Universe {
                 density: 0,
             })
             .collect();
-        let rng: SplitMix64 = SeedableRng::seed_from_u64(0x734f6b89de5f83cc);
+        let temps = vec![AMBIENT_TEMP; (width * height) as usize];
+        let rng: SplitMix64 = SeedableRng::seed_from_u64(seed);
         Universe {
             width,
             height,
             cells,
-            undo_stack: VecDeque::with_capacity(50),
+            undo_stack: VecDeque::with_capacity(UNDO_LIMIT),
+            redo_stack: Vec::new(),
             burns,
             winds,
+            temps,
             generation: 0,
             rng,
+            seed,
+            dirty: Some((0, width - 1)),
+            registry: Arc::new(SpeciesRegistry::default()),
+            recorder: None,
+            chunks: ChunkMap::new(width, height),
+            schedule: TickSchedule::Serial,
+            deterministic: false,
+            regions: Vec::new(),
+            touched: None,
+            boundaries: Boundaries::default(),
+            emitters: Vec::new(),
+            blasts: Vec::new(),
+        }
+    }
+
+    // 改变宇宙大小，保留原有细胞：左下角对齐（地面保持在底部），多出的部分裁掉，不足的部分补空。
+    // 撤销快照的尺寸不再匹配，所以清空撤销栈；录制中会把调整后的世界整个写进录像。
+    // rng 不重置：录像里的 Load 带着种子和 rng 状态，回放加载后的 rng 与这里一致。
+    pub fn resize(&mut self, width: i32, height: i32) {
+        if width <= 0 || height <= 0 || (width, height) == (self.width, self.height) {
+            return;
+        }
+        if !WorldConfig::valid_size(width as u32, height as u32) {
+            return;
+        }
+        let mut resized = Universe::with_seed(width, height, self.seed);
+        let dy = height - self.height;
+        for x in 0..width.min(self.width) {
+            for y in 0..height {
+                let old_y = y - dy;
+                if old_y < 0 || old_y >= self.height {
+                    continue;
+                }
+                let i = resized.get_index(x, y);
+                resized.cells[i] = self.get_cell(x, old_y);
+                resized.temps[i] = self.temps[self.get_index(x, old_y)];
+            }
+        }
+        self.width = width;
+        self.height = height;
+        self.cells = resized.cells;
+        self.winds = resized.winds;
+        self.burns = resized.burns;
+        self.temps = resized.temps;
+        self.chunks = resized.chunks;
+        // 矩形发射器跟着细胞一起移动，贴边的发射器本来就跟着边走
+        for emitter in self.emitters.iter_mut() {
+            if let EmitterArea::Rect { y, .. } = &mut emitter.area {
+                *y += dy;
+            }
+        }
+        self.flush_undos();
+        self.mark_all_dirty();
+        if self.recorder.is_some() {
+            // 回放时由 replace_universe 整个换上这份世界，包括 generation、种子和 rng 状态
+            let world = encode_world(self);
+            self.record(ReplayEvent::Load(world));
+        }
+    }
+
+    // 把 (x0, y0) 开始的 width x height 区域装进 region，作为一个独立的小宇宙给分块 tick 使用。
+    // region 是复用的缓冲，只覆盖分块 tick 会用到的字段；rng 由分块 tick 按块的位置设置
+    pub(crate) fn fill_region(&self, region: &mut Universe, x0: i32, y0: i32, width: i32, height: i32) {
+        region.cells.clear();
+        region.winds.clear();
+        region.burns.clear();
+        region.temps.clear();
+        for x in x0..x0 + width {
+            let start = self.get_index(x, y0);
+            let end = start + height as usize;
+            region.cells.extend_from_slice(&self.cells[start..end]);
+            region.winds.extend_from_slice(&self.winds[start..end]);
+            region.burns.extend_from_slice(&self.burns[start..end]);
+            region.temps.extend_from_slice(&self.temps[start..end]);
+        }
+        if (region.width, region.height) != (width, height) {
+            region.chunks = ChunkMap::new(width, height);
+        }
+        region.width = width;
+        region.height = height;
+        region.generation = self.generation;
+        region.seed = self.seed;
+        region.dirty = None;
+        region.registry = self.registry.clone();
+        region.boundaries = self.boundaries;
+        // 发射器只在整个宇宙的 tick 开始时处理，区域里始终没有
+        region.blasts.clear();
+        region.touched.get_or_insert_with(Vec::new).clear();
+    }
+
+    // 分块的区域里记下被写过的格子
+    pub(crate) fn touch(&mut self, i: usize) {
+        if let Some(touched) = self.touched.as_mut() {
+            touched.push(i);
+        }
+    }
+
+    // 这次运行的结果是否与调度方式和线程数无关（录像据此决定回放时能不能换调度）
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic || self.schedule != TickSchedule::Serial
+    }
+
+    // 用新种子重置 rng，之后的随机序列与 with_seed(seed) 新建的宇宙相同
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = SeedableRng::seed_from_u64(seed);
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // rng 的内部状态，存档时保存。SplitMix64 不公开状态，但它的输出函数是双射，可以从下一个输出反推出来
+    pub fn rng_state(&self) -> u64 {
+        let output = self.rng.clone().next_u64();
+        splitmix_unmix(output).wrapping_sub(SPLITMIX_GAMMA)
+    }
+
+    // 恢复 rng_state 保存的状态，种子不变
+    pub fn set_rng_state(&mut self, state: u64) {
+        self.rng = SeedableRng::seed_from_u64(state);
+    }
+
+    // 细胞、温度、generation 和 rng 状态的 FNV-1a 校验和，录像用它检查回放是否失步
+    pub fn checksum(&self) -> u64 {
+        let mut hash = FNV_OFFSET;
+        let mut feed = |bytes: &[u8]| {
+            for &byte in bytes {
+                hash = (hash ^ byte as u64).wrapping_mul(FNV_PRIME);
+            }
+        };
+        for cell in self.cells.iter() {
+            feed(&[cell.species as u8, cell.ra, cell.rb, cell.clock]);
         }
+        for temp in self.temps.iter() {
+            feed(&temp.to_le_bytes());
+        }
+        feed(&[self.generation]);
+        feed(&self.rng_state().to_le_bytes());
+        hash
+    }
+
+    // 录像只保存自定义材质，其余物种的参数是写死的
+    pub fn set_registry(&mut self, registry: SpeciesRegistry) {
+        if registry.custom_material() != self.registry.custom_material() {
+            self.record(ReplayEvent::Material(registry.custom_material().clone()));
+        }
+        self.registry = Arc::new(registry);
+    }
+
+    // 录制中时把事件写进录像
+    pub fn record(&mut self, event: ReplayEvent) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.push(event);
+        }
+    }
+
+    // 标记第 x 列需要重新上传
+    pub fn mark_dirty(&mut self, x: i32) {
+        self.dirty = match self.dirty {
+            Some((x0, x1)) => Some((x0.min(x), x1.max(x))),
+            None => Some((x, x)),
+        };
+    }
+
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty = Some((0, self.width - 1));
+    }
+
+    // 取出并清空脏区域
+    pub fn take_dirty(&mut self) -> Option<(i32, i32)> {
+        self.dirty.take()
+    }
+}
+
+// 64 位 FNV-1a 的初始值和乘数
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// SplitMix64 每次输出前状态增加的常数
+const SPLITMIX_GAMMA: u64 = 0x9e3779b97f4a7c15;
+
+// SplitMix64 输出函数的逆：z = mix(x) 时返回 x
+fn splitmix_unmix(z: u64) -> u64 {
+    let x = unxorshift(z, 31);
+    let x = unxorshift(x.wrapping_mul(mul_inverse(0x94d049bb133111eb)), 27);
+    unxorshift(x.wrapping_mul(mul_inverse(0xbf58476d1ce4e5b9)), 30)
+}
+
+// z = x ^ (x >> shift) 时返回 x
+fn unxorshift(z: u64, shift: u32) -> u64 {
+    let mut x = z;
+    for _ in 0..64 / shift {
+        x = z ^ (x >> shift);
     }
+    x
+}
+
+// 奇数 a 在模 2^64 下的乘法逆元（牛顿迭代，每次正确的位数翻倍）
+fn mul_inverse(a: u64) -> u64 {
+    let mut inverse = a;
+    for _ in 0..5 {
+        inverse = inverse.wrapping_mul(2u64.wrapping_sub(a.wrapping_mul(inverse)));
+    }
+    inverse
 }
 
 //private methods
 impl Universe {
-    fn get_index(&self, x: i32, y: i32) -> usize {
+    // 第 cy 行块覆盖的 y
+    pub(crate) fn chunk_rows(&self, cy: i32) -> Range<i32> {
+        cy * CHUNK_SIZE..((cy + 1) * CHUNK_SIZE).min(self.height)
+    }
+
+    pub(crate) fn get_index(&self, x: i32, y: i32) -> usize {
         (x * self.height + y) as usize
     }
 
-    fn get_cell(&self, x: i32, y: i32) -> Cell {
+    pub(crate) fn get_cell(&self, x: i32, y: i32) -> Cell {
         let i = self.get_index(x, y);
         return self.cells[i];
     }
 
-    fn get_wind(&self, x: i32, y: i32) -> Wind {
+    pub(crate) fn get_wind(&self, x: i32, y: i32) -> Wind {
         let i = self.get_index(x, y);
         return self.winds[i];
     }
 
-    fn blow_wind(cell: Cell, wind: Wind, mut api: SandApi) {
-        if cell.clock - api.universe.generation == 1 {
+    pub(crate) fn blow_wind(cell: Cell, wind: Wind, mut api: SandApi) {
+        if cell.clock.wrapping_sub(api.universe.generation) == 1 {
             return;
         }
         if cell.species == Species::Empty {
@@ -412,42 +839,7 @@ impl Universe {
         let mut dx = 0;
         let mut dy = 0;
 
-        let threshold = match cell.species {
-            Species::Empty => 500,
-            Species::Wall => 500,
-            Species::Cloner => 500,
-
-            Species::Stone => 70,
-            Species::Wood => 70,
-
-            Species::Plant => 60,
-            Species::Lava => 60,
-            Species::Ice => 60,
-
-            Species::Fungus => 54,
-
-            Species::Oil => 50,
-
-            // Intentionally left out and covered by the default case
-            // Species::Water => 40,
-            // Species::Acid => 40,
-            Species::Seed => 35,
-
-            Species::Sand => 30,
-            Species::Mite => 30,
-            Species::Rocket => 30,
-
-            Species::Dust => 10,
-            Species::Fire => 5,
-            Species::Gas => 5,
-            /*
-             Some hacked species values exist outside of the enum values.
-             Making sure the default case is emitted allows "BELP" to have a defined wind threshold.
-             Originally, threshold was a hardcoded value, so this preserves that original glitch behavior.
-             See: https://sandspiel.club/#eMlYGC52XIto0NM1WjaJ
-            */
-            _ => 40,
-        };
+        let threshold = api.universe.registry.wind_threshold(cell.species);
 
         let wx = (wind.dy as i32) - 126;
         let wy = (wind.dx as i32) - 126;
@@ -468,14 +860,7 @@ impl Universe {
             api.set(0, 0, EMPTY_CELL);
             if dy == -1
                 && api.get(dx, -2).species == Species::Empty
-                && (cell.species == Species::Sand
-                || cell.species == Species::Water
-                || cell.species == Species::Lava
-                || cell.species == Species::Acid
-                || cell.species == Species::Mite
-                || cell.species == Species::Dust
-                || cell.species == Species::Oil
-                || cell.species == Species::Rocket)
+                && api.universe.registry.wind_jump(cell.species)
             {
                 dy = -2;
             }
@@ -483,11 +868,55 @@ impl Universe {
             return;
         }
     }
-    fn update_cell(cell: Cell, api: SandApi) {
-        if cell.clock - api.universe.generation == 1 {
+    pub(crate) fn update_cell(cell: Cell, api: SandApi) {
+        if cell.clock.wrapping_sub(api.universe.generation) == 1 {
             return;
         }
 
         cell.update(api);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sand_falls_straight_down() {
+        let mut universe = Universe::new(64, 64);
+        universe.place(20, 20, Species::Sand);
+        for tick in 1..=5 {
+            universe.tick();
+            assert_eq!(universe.get_cell(20, 20 + tick).species, Species::Sand);
+        }
+    }
+
+    #[test]
+    fn wind_jump_comes_from_the_registry() {
+        let lift = Wind { dx: 26, dy: 126, pressure: 0, density: 0 };
+        let blow = |universe: &mut Universe| {
+            universe.place(8, 8, Species::Sand);
+            let cell = universe.get_cell(8, 8);
+            Universe::blow_wind(cell, lift, SandApi { universe: &mut *universe, x: 8, y: 8 });
+            universe.cells.iter().position(|cell| cell.species == Species::Sand)
+        };
+        let mut universe = Universe::new(16, 16);
+        assert_eq!(blow(&mut universe), Some(universe.get_index(8, 6)));
+
+        let mut registry = SpeciesRegistry::default();
+        let mut sand = registry.get(Species::Sand).unwrap().clone();
+        sand.wind_jump = false;
+        registry.register(sand);
+        let mut universe = Universe::new(16, 16);
+        universe.set_registry(registry);
+        assert_eq!(blow(&mut universe), Some(universe.get_index(8, 7)));
+    }
+
+    #[test]
+    fn reset_calms_winds() {
+        let mut universe = Universe::new(8, 8);
+        universe.winds[0] = Wind { dx: 0, dy: 0, pressure: 0, density: 0 };
+        universe.reset();
+        assert!(universe.winds.iter().all(|&wind| wind == CALM_WIND));
+    }
+}
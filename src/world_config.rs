@@ -0,0 +1,78 @@
+use bevy::prelude::*;
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use crate::universe::Universe;
+use crate::world_file::MAX_WORLD_CELLS;
+
+// 宇宙尺寸和显示比例的唯一来源。Universe、BootState、SandUniform 和 PipelineSand 的纹理都从这里读取，
+// 修改 width / height 会调整 Universe 的大小（保留原有细胞，见 Universe::resize），渲染纹理随之重建。
+// 加载不同尺寸的存档时，反过来用 Universe 的尺寸更新这里。
+pub const DEFAULT_WORLD_WIDTH: u32 = 300;
+pub const DEFAULT_WORLD_HEIGHT: u32 = 300;
+
+#[derive(Resource, Clone, Copy, Debug, PartialEq, ExtractResource)]
+pub struct WorldConfig {
+    pub width: u32,
+    pub height: u32,
+    // 一个细胞在世界坐标中占用的大小，宇宙以原点为中心
+    pub cell_scale: f32,
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        Self {
+            width: DEFAULT_WORLD_WIDTH,
+            height: DEFAULT_WORLD_HEIGHT,
+            cell_scale: 1.0,
+        }
+    }
+}
+
+impl WorldConfig {
+    // 宽高都不为 0，格子数不超过存档的上限 MAX_WORLD_CELLS，width * height 也就不会溢出 i32
+    pub fn valid_size(width: u32, height: u32) -> bool {
+        width > 0 && height > 0 && width as u64 * height as u64 <= MAX_WORLD_CELLS
+    }
+}
+
+pub struct WorldConfigPlugin;
+
+impl Plugin for WorldConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<WorldConfig>()
+            .add_plugins(ExtractResourcePlugin::<WorldConfig>::default())
+            .add_systems(PreUpdate, sync_world_config);
+    }
+
+    fn finish(&self, app: &mut App) {
+        // 渲染世界的 FromWorld 在第一次提取之前运行，需要先放一份进去
+        let config = *app.world.resource::<WorldConfig>();
+        if let Ok(render_app) = app.get_sub_app_mut(bevy::render::RenderApp) {
+            render_app.insert_resource(config);
+        }
+    }
+}
+
+// 配置被修改时调整宇宙大小；否则宇宙尺寸变了（加载存档）就更新配置
+fn sync_world_config(
+    mut config: ResMut<WorldConfig>,
+    mut universe: ResMut<Universe>,
+) {
+    let size = (universe.width as u32, universe.height as u32);
+    if size == (config.width, config.height) {
+        return;
+    }
+    if config.is_changed() && !config.is_added() {
+        if !WorldConfig::valid_size(config.width, config.height) {
+            eprintln!("world size {}x{} is too large, keeping {}x{}", config.width, config.height, size.0, size.1);
+            config.width = size.0;
+            config.height = size.1;
+            return;
+        }
+        universe.resize(config.width as i32, config.height as i32);
+        println!("resized universe to {}x{}", config.width, config.height);
+    } else {
+        config.width = size.0;
+        config.height = size.1;
+    }
+}
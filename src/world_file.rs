@@ -0,0 +1,408 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use bevy::prelude::*;
+use crate::boundary::Boundaries;
+use crate::cli::CliArgs;
+use crate::emitter::{decode_emitters, encode_emitters};
+use crate::replay::{not_replaying, ReplayEvent};
+use crate::rle::Rle;
+use crate::universe::{Cell, CellData, Universe};
+use crate::world_config::WorldConfig;
+use crate::world_image::{decode_png, encode_png};
+
+// 世界存档格式（小端）：
+//   magic      4 字节 "SSPW"
+//   version    u16
+//   width      u32
+//   height     u32
+//   generation u8
+//   seed       u64 + rng 状态 u64，加载后从存档时的 rng 状态继续，场景可以原样交给别人复现
+//   boundaries 4 字节：左、右、上、下的边界规则
+//   emitters   发射器列表（格式见 emitter.rs）
+//   RLE 压缩的 CellData：runs 个数 u32 + runs（u32），values 个数 u32 + values（每个 4 字节）
+//   RLE 压缩的温度：格式同上，values 每个是 i16
+// 风场和燃烧场是每帧重新计算的，不保存。
+pub const WORLD_MAGIC: [u8; 4] = *b"SSPW";
+pub const WORLD_VERSION: u16 = 1;
+
+// 宇宙格子数的上限，防止损坏的文件让我们分配过大的内存。PNG 存档、--size 和调整大小也使用同一个上限（见 WorldConfig::valid_size）
+pub(crate) const MAX_WORLD_CELLS: u64 = 1 << 26;
+
+#[derive(Debug)]
+pub enum WorldFileError {
+    Io(io::Error),
+    Image(image::ImageError),
+    BadMagic,
+    UnsupportedVersion(u16),
+    Corrupt(&'static str),
+}
+
+impl fmt::Display for WorldFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorldFileError::Io(err) => write!(f, "io error: {}", err),
+            WorldFileError::Image(err) => write!(f, "image error: {}", err),
+            WorldFileError::BadMagic => write!(f, "not a world file"),
+            WorldFileError::UnsupportedVersion(v) => write!(f, "unsupported world file version {}", v),
+            WorldFileError::Corrupt(what) => write!(f, "corrupt world file: {}", what),
+        }
+    }
+}
+
+impl From<io::Error> for WorldFileError {
+    fn from(err: io::Error) -> Self {
+        WorldFileError::Io(err)
+    }
+}
+
+impl From<image::ImageError> for WorldFileError {
+    fn from(err: image::ImageError) -> Self {
+        WorldFileError::Image(err)
+    }
+}
+
+pub fn encode_world(universe: &Universe) -> Vec<u8> {
+    let data: Vec<CellData> = universe.cells.iter().map(|cell| cell.to_cell_data()).collect();
+    let rle = Rle::encode(&data);
+
+    let mut out = Vec::with_capacity(39 + rle.runs.len() * 4 + rle.values.len() * 4);
+    out.extend_from_slice(&WORLD_MAGIC);
+    out.extend_from_slice(&WORLD_VERSION.to_le_bytes());
+    out.extend_from_slice(&(universe.width as u32).to_le_bytes());
+    out.extend_from_slice(&(universe.height as u32).to_le_bytes());
+    out.push(universe.generation);
+    out.extend_from_slice(&universe.seed().to_le_bytes());
+    out.extend_from_slice(&universe.rng_state().to_le_bytes());
+    out.extend_from_slice(&universe.boundaries.to_bytes());
+    encode_emitters(&mut out, &universe.emitters);
+
+    out.extend_from_slice(&(rle.runs.len() as u32).to_le_bytes());
+    for run in rle.runs.iter() {
+        out.extend_from_slice(&run.to_le_bytes());
+    }
+    out.extend_from_slice(&(rle.values.len() as u32).to_le_bytes());
+    out.extend_from_slice(bytemuck::cast_slice(rle.values.as_slice()));
+
+    let temps = Rle::encode(&universe.temps);
+    out.extend_from_slice(&(temps.runs.len() as u32).to_le_bytes());
+    for run in temps.runs.iter() {
+        out.extend_from_slice(&run.to_le_bytes());
+    }
+    out.extend_from_slice(&(temps.values.len() as u32).to_le_bytes());
+    for value in temps.values.iter() {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out
+}
+
+pub fn decode_world(bytes: &[u8]) -> Result<Universe, WorldFileError> {
+    let mut reader = ByteReader::new(bytes);
+    if reader.take(4)? != WORLD_MAGIC {
+        return Err(WorldFileError::BadMagic);
+    }
+    let version = reader.u16()?;
+    if version != WORLD_VERSION {
+        return Err(WorldFileError::UnsupportedVersion(version));
+    }
+    let width = reader.u32()?;
+    let height = reader.u32()?;
+    if !WorldConfig::valid_size(width, height) {
+        return Err(WorldFileError::Corrupt("bad dimensions"));
+    }
+    let cell_count = width as u64 * height as u64;
+    let generation = reader.u8()?;
+    let seed = reader.u64()?;
+    let rng_state = reader.u64()?;
+    let b = reader.take(4)?;
+    let boundaries =
+        Boundaries::from_bytes([b[0], b[1], b[2], b[3]]).ok_or(WorldFileError::Corrupt("unknown boundary"))?;
+    let emitters = decode_emitters(&mut reader)?;
+
+    let data = read_rle(&mut reader, cell_count as usize, 4, "cell count does not match dimensions", |b| {
+        CellData { species: b[0], ra: b[1], rb: b[2], clock: b[3] }
+    })?;
+
+    let temps = read_rle(&mut reader, cell_count as usize, 2, "temperature count does not match dimensions", |b| {
+        i16::from_le_bytes([b[0], b[1]])
+    })?;
+
+    let mut universe = Universe::new(width as i32, height as i32);
+    universe.cells = data.into_iter().map(Cell::from).collect();
+    universe.temps = temps;
+    universe.generation = generation;
+    universe.boundaries = boundaries;
+    universe.emitters = emitters;
+    universe.reseed(seed);
+    universe.set_rng_state(rng_state);
+    Ok(universe)
+}
+
+// 读取一段 RLE：runs 个数 u32 + runs，values 个数 u32 + 每个 size 字节的 values。
+// 解码前检查所有段的长度之和正好是 len，不对时返回 Corrupt(mismatch)。
+fn read_rle<T: Copy + PartialEq>(
+    reader: &mut ByteReader,
+    len: usize,
+    size: usize,
+    mismatch: &'static str,
+    value: impl Fn(&[u8]) -> T,
+) -> Result<Vec<T>, WorldFileError> {
+    let run_count = reader.u32()? as usize;
+    let mut runs = Vec::with_capacity(run_count.min(len));
+    for _ in 0..run_count {
+        runs.push(reader.u32()?);
+    }
+    let value_count = reader.u32()? as usize;
+    let values = reader
+        .take(value_count.checked_mul(size).ok_or(WorldFileError::Corrupt("value count"))?)?
+        .chunks_exact(size)
+        .map(value)
+        .collect();
+    let rle = Rle { runs, values, len };
+    if !rle.is_valid() {
+        return Err(WorldFileError::Corrupt(mismatch));
+    }
+    Ok(rle.decode())
+}
+
+// .png 扩展名的路径按图片格式（world_image）读写，其余使用二进制存档格式
+fn is_png(path: &Path) -> bool {
+    path.extension().map_or(false, |ext| ext.eq_ignore_ascii_case("png"))
+}
+
+pub fn save_world(universe: &Universe, path: &Path) -> Result<(), WorldFileError> {
+    let bytes = if is_png(path) {
+        encode_png(universe)?
+    } else {
+        encode_world(universe)
+    };
+    fs::write(path, bytes)?;
+    Ok(())
+}
+
+// 从文件加载并替换当前宇宙
+pub fn load_world(universe: &mut Universe, path: &Path) -> Result<(), WorldFileError> {
+    let bytes = fs::read(path)?;
+    let mut loaded = if is_png(path) {
+        decode_png(&bytes)?
+    } else {
+        decode_world(&bytes)?
+    };
+    // PNG 没有 rng 状态，从当前种子重新开始
+    if is_png(path) {
+        loaded.reseed(universe.seed());
+    }
+    replace_universe(universe, loaded);
+    Ok(())
+}
+
+// 用加载的宇宙替换当前宇宙，保留当前的物种注册表、tick 调度和录像。
+// 种子和 rng 状态、边界规则和发射器跟随存档（PNG 是全墙，没有发射器）。
+// 录制中加载的世界会整个写进录像。
+pub fn replace_universe(universe: &mut Universe, mut loaded: Universe) {
+    loaded.registry = universe.registry.clone();
+    loaded.schedule = universe.schedule;
+    loaded.deterministic = universe.deterministic;
+    let recorder = universe.recorder.take();
+    *universe = loaded;
+    if let Some(mut recorder) = recorder {
+        recorder.push(ReplayEvent::Load(encode_world(universe)));
+        universe.recorder = Some(recorder);
+    }
+}
+
+pub(crate) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    pub fn take(&mut self, n: usize) -> Result<&'a [u8], WorldFileError> {
+        let end = self.pos.checked_add(n).filter(|&end| end <= self.bytes.len())
+            .ok_or(WorldFileError::Corrupt("unexpected end of file"))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, WorldFileError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, WorldFileError> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, WorldFileError> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub fn i32(&mut self) -> Result<i32, WorldFileError> {
+        Ok(self.u32()? as i32)
+    }
+
+    pub fn u64(&mut self) -> Result<u64, WorldFileError> {
+        let lo = self.u32()? as u64;
+        let hi = self.u32()? as u64;
+        Ok(lo | hi << 32)
+    }
+}
+
+// 存档插件：Ctrl+S 保存到 WorldFilePath，Ctrl+O 从 WorldFilePath 加载；
+// Ctrl+E / Ctrl+I 以同名 .png 图片导出/导入。
+// 启动参数 --load 在启动时加载，--save 指定保存路径。
+pub struct WorldFilePlugin;
+
+#[derive(Resource)]
+pub struct WorldFilePath(pub PathBuf);
+
+impl Plugin for WorldFilePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, load_world_on_startup)
+            .add_systems(Update, world_file_input_system.run_if(not_replaying));
+    }
+}
+
+pub fn load_world_on_startup(
+    mut commands: Commands,
+    args: Res<CliArgs>,
+    mut universe: ResMut<Universe>,
+) {
+    commands.insert_resource(WorldFilePath(args.save_path.clone()));
+    if let Some(path) = args.load.as_ref() {
+        // 宇宙由 CliArgs::build_universe 创建，--emitter 已经按注册表解析好了
+        let emitters = universe.emitters.clone();
+        load_with_log(&mut universe, path);
+        // 命令行指定的边界和发射器优先于存档
+        if let Some(boundaries) = args.boundaries {
+            universe.boundaries = boundaries;
+        }
+        if !emitters.is_empty() {
+            universe.emitters = emitters;
+        }
+    }
+}
+
+fn world_file_input_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    path: Option<Res<WorldFilePath>>,
+    mut universe: ResMut<Universe>,
+) {
+    let Some(path) = path else {
+        return;
+    };
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight, KeyCode::SuperLeft, KeyCode::SuperRight]);
+    if !ctrl {
+        return;
+    }
+    let png_path = path.0.with_extension("png");
+    if keys.just_pressed(KeyCode::KeyS) {
+        save_with_log(&universe, &path.0);
+    }
+    if keys.just_pressed(KeyCode::KeyO) {
+        load_with_log(&mut universe, &path.0);
+    }
+    if keys.just_pressed(KeyCode::KeyE) {
+        save_with_log(&universe, &png_path);
+    }
+    if keys.just_pressed(KeyCode::KeyI) {
+        load_with_log(&mut universe, &png_path);
+    }
+}
+
+pub fn save_with_log(universe: &Universe, path: &Path) {
+    match save_world(universe, path) {
+        Ok(()) => println!("saved world to {}", path.display()),
+        Err(err) => eprintln!("failed to save {}: {}", path.display(), err),
+    }
+}
+
+pub fn load_with_log(universe: &mut Universe, path: &Path) {
+    match load_world(universe, path) {
+        Ok(()) => println!("loaded world from {}", path.display()),
+        Err(err) => eprintln!("failed to load {}: {}", path.display(), err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+    use super::*;
+    use crate::emitter::Emitter;
+    use crate::species::Species;
+
+    fn sample_world() -> Universe {
+        let mut universe = Universe::with_seed(70, 50, 42);
+        universe.paint(20, 20, 10, Species::Sand);
+        universe.paint(50, 10, 8, Species::Water);
+        universe.paint(35, 40, 6, Species::Lava);
+        universe.boundaries = Boundaries::parse("wrap,wrap,wall,void").unwrap();
+        universe.emitters = vec![Emitter::parse("water:0.2@top:30:10", &universe.registry).unwrap()];
+        for _ in 0..10 {
+            universe.tick();
+        }
+        universe
+    }
+
+    #[test]
+    fn world_round_trips() {
+        let universe = sample_world();
+        let loaded = decode_world(&encode_world(&universe)).unwrap();
+        assert_eq!((loaded.width, loaded.height), (universe.width, universe.height));
+        assert!(loaded.cells == universe.cells);
+        assert_eq!(loaded.temps, universe.temps);
+        assert_eq!(loaded.generation, universe.generation);
+        assert_eq!(loaded.boundaries, universe.boundaries);
+        assert_eq!(loaded.emitters, universe.emitters);
+        assert_eq!(loaded.checksum(), universe.checksum());
+    }
+
+    #[test]
+    fn world_keeps_the_rng_state() {
+        let mut universe = sample_world();
+        let mut loaded = decode_world(&encode_world(&universe)).unwrap();
+        assert_eq!(loaded.seed(), universe.seed());
+        assert_eq!(loaded.rng_state(), universe.rng_state());
+        assert_eq!(loaded.rng.gen::<u64>(), universe.rng.gen::<u64>());
+    }
+
+    #[test]
+    fn truncated_world_is_rejected() {
+        let bytes = encode_world(&sample_world());
+        for len in 0..bytes.len() {
+            assert!(decode_world(&bytes[..len]).is_err(), "accepted {} of {} bytes", len, bytes.len());
+        }
+    }
+
+    #[test]
+    fn bad_header_is_rejected() {
+        let bytes = encode_world(&sample_world());
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(matches!(decode_world(&bad_magic), Err(WorldFileError::BadMagic)));
+        let mut bad_version = bytes.clone();
+        bad_version[4..6].copy_from_slice(&(WORLD_VERSION + 1).to_le_bytes());
+        assert!(matches!(decode_world(&bad_version), Err(WorldFileError::UnsupportedVersion(_))));
+        let mut too_large = bytes;
+        too_large[6..10].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(decode_world(&too_large), Err(WorldFileError::Corrupt(_))));
+    }
+
+    #[test]
+    fn oversized_rle_run_is_rejected() {
+        // 全空的 4x4 宇宙：35 字节的头部和空的发射器列表之后是细胞的 RLE，只有一段 16 个的重复段
+        let mut bytes = encode_world(&Universe::with_seed(4, 4, 1));
+        assert_eq!(bytes[39..43], 1u32.to_le_bytes());
+        assert_eq!(bytes[43..47], (1u32 << 31 | 16).to_le_bytes());
+        bytes[43..47].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(decode_world(&bytes), Err(WorldFileError::Corrupt(_))));
+    }
+}
@@ -0,0 +1,45 @@
+use std::io::Cursor;
+use image::{ImageFormat, RgbaImage};
+use crate::universe::{Cell, CellData, Universe};
+use crate::world_config::WorldConfig;
+use crate::world_file::WorldFileError;
+
+// 以 PNG 图片导入导出世界。CellData 正好是 4 个字节，与 RGBA8 一个像素一一对应：
+//   R = species, G = ra, B = rb, A = clock
+// 和 sand.wgsl 从红色通道解码物种的方式相同，也是原版 sandspiel 分享链接使用的编码。
+// 像素 (x, y) 对应细胞 (x, y)，所以图片的方向与屏幕上看到的一致。
+
+pub fn encode_png(universe: &Universe) -> Result<Vec<u8>, WorldFileError> {
+    let width = universe.width as u32;
+    let height = universe.height as u32;
+    let mut image = RgbaImage::new(width, height);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let data = universe.cells[(x * height + y) as usize].to_cell_data();
+        pixel.0 = [data.species, data.ra, data.rb, data.clock];
+    }
+
+    let mut out = Cursor::new(Vec::new());
+    image.write_to(&mut out, ImageFormat::Png)?;
+    Ok(out.into_inner())
+}
+
+pub fn decode_png(bytes: &[u8]) -> Result<Universe, WorldFileError> {
+    // 先只读图片头里的尺寸，超过上限的图片不解码
+    let (width, height) = image::io::Reader::with_format(Cursor::new(bytes), ImageFormat::Png).into_dimensions()?;
+    if !WorldConfig::valid_size(width, height) {
+        return Err(WorldFileError::Corrupt("bad dimensions"));
+    }
+
+    let image = image::load_from_memory_with_format(bytes, ImageFormat::Png)?;
+    // 只有 RGBA8 的图片才是无损的世界数据
+    let Some(image) = image.as_rgba8() else {
+        return Err(WorldFileError::Corrupt("world images must be 8-bit RGBA"));
+    };
+    let (width, height) = image.dimensions();
+    let mut universe = Universe::new(width as i32, height as i32);
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let [species, ra, rb, clock] = pixel.0;
+        universe.cells[(x * height + y) as usize] = Cell::from(CellData { species, ra, rb, clock });
+    }
+    Ok(universe)
+}